@@ -0,0 +1,430 @@
+//! Implements `jupiter-amm-interface` for the amm program's pools, so that
+//! Jupiter, and anything else built on it, can quote swaps through them.
+//!
+//! Quotes run the program's own `Amm::swap` against a copy of the pool, after
+//! the same TWAP update and followed by the same circuit breaker check as the
+//! `swap` instruction, so a quote is exactly what the instruction pays out
+//! for the same pool state and slot. Swaps only go through the curve: they
+//! don't pass an order book or a referrer.
+//!
+//! This is quote-only: Jupiter can't route swaps through these pools yet.
+//! Its router calls each DEX through a `Swap` variant that fixes the program
+//! and instruction data it sends, and no version of `jupiter-amm-interface`
+//! has one for this program. The closest, `Futarchy` (from 0.6), is for
+//! MetaDAO's futarchy program, whose `swap` has a different ABI, and needs
+//! Solana 2. So `get_swap_and_account_metas` errors instead of handing Jupiter
+//! a variant for another program. Callers that want to swap with a quote can
+//! build the instruction with `swap_instruction`.
+
+use amm::instructions::SwapArgs;
+use amm::state::{Amm as AmmState, SwapType};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token;
+use anyhow::{anyhow, bail, Result};
+use jupiter_amm_interface::{
+  try_get_account_data, AccountMap, Amm, AmmContext, ClockRef, KeyedAccount, Quote, QuoteParams, SwapAndAccountMetas, SwapMode,
+  SwapParams,
+};
+use rust_decimal::Decimal;
+use std::sync::atomic::Ordering;
+
+/// The pools' 1% swap fee, which `Amm::swap` takes out of the input.
+const SWAP_FEE_PCT: Decimal = Decimal::ONE;
+
+#[derive(Clone)]
+pub struct JupiterAmm {
+  key: Pubkey,
+  state: AmmState,
+  clock_ref: ClockRef,
+}
+
+impl JupiterAmm {
+  /// Which way a swap from `input_mint` to `output_mint` goes through the pool.
+  fn swap_type(&self, input_mint: Pubkey, output_mint: Pubkey) -> Result<SwapType> {
+    let AmmState {
+      base_mint, quote_mint, ..
+    } = self.state;
+
+    match (input_mint, output_mint) {
+      (input_mint, output_mint) if input_mint == quote_mint && output_mint == base_mint => Ok(SwapType::Buy),
+      (input_mint, output_mint) if input_mint == base_mint && output_mint == quote_mint => Ok(SwapType::Sell),
+      _ => bail!("{input_mint} to {output_mint} isn't traded by pool {}", self.key),
+    }
+  }
+
+  /// Runs a swap of `input_amount` on a copy of the pool the way the `swap`
+  /// instruction would at `current_slot`, returning the output amount.
+  pub fn simulate_swap(&self, input_amount: u64, swap_type: SwapType, current_slot: u64) -> Result<u64> {
+    let mut state = self.state;
+
+    state.update_twap(current_slot);
+
+    let output_amount = state.swap(input_amount, swap_type).map_err(|err| anyhow!("{err}"))?;
+
+    state.check_circuit_breaker().map_err(|err| anyhow!("{err}"))?;
+
+    Ok(output_amount)
+  }
+
+  /// Builds a standalone `swap` instruction, for callers that don't go
+  /// through Jupiter's program.
+  pub fn swap_instruction(&self, swap_params: &SwapParams, output_amount_min: u64) -> Result<Instruction> {
+    let swap_type = self.swap_type(swap_params.source_mint, swap_params.destination_mint)?;
+
+    Ok(Instruction {
+      program_id: amm::ID,
+      accounts: self.swap_account_metas(swap_params, swap_type),
+      data: amm::instruction::Swap {
+        args: SwapArgs {
+          swap_type,
+          input_amount: swap_params.in_amount,
+          output_amount_min,
+          max_price_impact_bps: None,
+          limit_price: None,
+        },
+      }
+      .data(),
+    })
+  }
+
+  fn swap_account_metas(&self, swap_params: &SwapParams, swap_type: SwapType) -> Vec<AccountMeta> {
+    let (user_base_account, user_quote_account) = match swap_type {
+      SwapType::Buy => (swap_params.destination_token_account, swap_params.source_token_account),
+      SwapType::Sell => (swap_params.source_token_account, swap_params.destination_token_account),
+    };
+
+    amm::accounts::Swap {
+      user: swap_params.token_transfer_authority,
+      amm: self.key,
+      user_base_account: Some(user_base_account),
+      user_quote_account: Some(user_quote_account),
+      vault_ata_base: get_associated_token_address(&self.key, &self.state.base_mint),
+      vault_ata_quote: get_associated_token_address(&self.key, &self.state.quote_mint),
+      referrer_account: None,
+      referrer: None,
+      order_book: None,
+      allowlist_entry: None,
+      native_mint: None,
+      native_sol_account: None,
+      token_program: token::ID,
+      system_program: None,
+    }
+    .to_account_metas(None)
+  }
+}
+
+impl Amm for JupiterAmm {
+  fn from_keyed_account(keyed_account: &KeyedAccount, amm_context: &AmmContext) -> Result<Self> {
+    let state = AmmState::try_from_account_data(&keyed_account.account.data)?;
+
+    Ok(Self {
+      key: keyed_account.key,
+      state,
+      clock_ref: amm_context.clock_ref.clone(),
+    })
+  }
+
+  fn label(&self) -> String {
+    "MetaDAO AMM".to_string()
+  }
+
+  fn program_id(&self) -> Pubkey {
+    amm::ID
+  }
+
+  fn key(&self) -> Pubkey {
+    self.key
+  }
+
+  fn get_reserve_mints(&self) -> Vec<Pubkey> {
+    vec![self.state.base_mint, self.state.quote_mint]
+  }
+
+  /// The reserves are tracked on the pool itself, so it's all that a quote
+  /// needs.
+  fn get_accounts_to_update(&self) -> Vec<Pubkey> {
+    vec![self.key]
+  }
+
+  fn update(&mut self, account_map: &AccountMap) -> Result<()> {
+    let data = try_get_account_data(account_map, &self.key)?;
+
+    self.state = AmmState::try_from_account_data(data)?;
+
+    Ok(())
+  }
+
+  /// Only exact-in quotes, see `supports_exact_out`.
+  fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
+    if quote_params.swap_mode == SwapMode::ExactOut {
+      bail!("pool {} only quotes exact-in swaps", self.key);
+    }
+
+    let swap_type = self.swap_type(quote_params.input_mint, quote_params.output_mint)?;
+    let current_slot = self.clock_ref.slot.load(Ordering::Relaxed);
+
+    let out_amount = self.simulate_swap(quote_params.amount, swap_type, current_slot)?;
+
+    Ok(Quote {
+      in_amount: quote_params.amount,
+      out_amount,
+      fee_amount: quote_params.amount / 100,
+      fee_mint: quote_params.input_mint,
+      fee_pct: SWAP_FEE_PCT,
+      ..Quote::default()
+    })
+  }
+
+  /// Always errors, since Jupiter has no `Swap` variant for these pools, see
+  /// the crate docs.
+  fn get_swap_and_account_metas(&self, _swap_params: &SwapParams) -> Result<SwapAndAccountMetas> {
+    bail!(
+      "Jupiter can't call pool {} until it has a `Swap` variant for it, use `swap_instruction` instead",
+      self.key
+    )
+  }
+
+  fn clone_amm(&self) -> Box<dyn Amm + Send + Sync> {
+    Box::new(self.clone())
+  }
+
+  fn get_accounts_len(&self) -> usize {
+    14
+  }
+
+  /// Sunset pools take no more swaps, batch-auction pools only take them
+  /// through their batch queue, and gated pools only from allowlisted users.
+  fn is_active(&self) -> bool {
+    !self.state.is_sunset() && !self.state.is_batch_auction() && self.state.gate_authority.is_none()
+  }
+}
+
+#[cfg(test)]
+mod jupiter_amm_tests {
+  use super::*;
+  use amm::state::{ObservationChangeMode, TwapOracle, PRICE_SCALE};
+  use anchor_lang::Discriminator;
+  use solana_sdk::account::Account;
+
+  fn pool() -> AmmState {
+    AmmState {
+      base_mint: Pubkey::new_unique(),
+      quote_mint: Pubkey::new_unique(),
+      base_amount: 2_000_000_000,
+      quote_amount: 5_000_000,
+      oracle: TwapOracle::new(0, PRICE_SCALE / 400, PRICE_SCALE, ObservationChangeMode::Absolute, None),
+      ..AmmState::default()
+    }
+  }
+
+  /// A pool's account data, the way the program lays it out.
+  fn account_data(state: &AmmState) -> Vec<u8> {
+    [&AmmState::DISCRIMINATOR[..], bytemuck::bytes_of(state)].concat()
+  }
+
+  fn jupiter_amm(state: &AmmState, slot: u64) -> JupiterAmm {
+    let data = account_data(state);
+
+    let keyed_account = KeyedAccount {
+      key: Pubkey::new_unique(),
+      account: Account {
+        lamports: 1_000_000,
+        data,
+        owner: amm::ID,
+        executable: false,
+        rent_epoch: 0,
+      },
+      params: None,
+    };
+
+    let amm_context = AmmContext {
+      clock_ref: ClockRef::default(),
+    };
+    amm_context.clock_ref.slot.store(slot, Ordering::Relaxed);
+
+    JupiterAmm::from_keyed_account(&keyed_account, &amm_context).unwrap()
+  }
+
+  fn quote(jupiter_amm: &JupiterAmm, amount: u64, input_mint: Pubkey, output_mint: Pubkey) -> Result<Quote> {
+    jupiter_amm.quote(&QuoteParams {
+      amount,
+      input_mint,
+      output_mint,
+      swap_mode: SwapMode::ExactIn,
+    })
+  }
+
+  #[test]
+  fn quotes() {
+    let state = pool();
+    let jupiter_amm = jupiter_amm(&state, 1_000);
+
+    // worked out by hand from the 2_000_000_000 base and 5_000_000 quote
+    // reserves, as in * 99 * output_reserve / (input_reserve * 100 + in * 99)
+    for (amount, out_amount) in [
+      (1, 395),
+      (100, 39_599),
+      (12_345, 4_876_699),
+      (1_000_000, 330_550_918),
+      (4_999_999, 994_974_774),
+    ] {
+      let quote = quote(&jupiter_amm, amount, state.quote_mint, state.base_mint).unwrap();
+
+      assert_eq!(quote.out_amount, out_amount);
+      assert_eq!(quote.in_amount, amount);
+      assert_eq!(quote.fee_amount, amount / 100);
+      assert_eq!(quote.fee_mint, state.quote_mint);
+    }
+
+    for (amount, out_amount) in [
+      (100, 0),
+      (1_000, 2),
+      (1_000_000, 2_473),
+      (12_345_678, 30_369),
+      (1_000_000_000, 1_655_518),
+    ] {
+      let quote = quote(&jupiter_amm, amount, state.base_mint, state.quote_mint).unwrap();
+
+      assert_eq!(quote.out_amount, out_amount);
+      assert_eq!(quote.fee_amount, amount / 100);
+      assert_eq!(quote.fee_mint, state.base_mint);
+    }
+
+    assert!(quote(&jupiter_amm, 100, state.quote_mint, Pubkey::new_unique()).is_err());
+
+    // `amount` would be read as an input
+    let exact_out = jupiter_amm.quote(&QuoteParams {
+      amount: 100,
+      input_mint: state.quote_mint,
+      output_mint: state.base_mint,
+      swap_mode: SwapMode::ExactOut,
+    });
+    assert!(exact_out.is_err());
+  }
+
+  #[test]
+  fn quotes_respect_the_circuit_breaker() {
+    let state = AmmState {
+      circuit_breaker_bps: Some(500).into(),
+      ..pool()
+    };
+    let jupiter_amm = jupiter_amm(&state, 1_000);
+
+    assert!(quote(&jupiter_amm, 10_000, state.quote_mint, state.base_mint).is_ok());
+    assert!(quote(&jupiter_amm, 1_000_000, state.quote_mint, state.base_mint).is_err());
+  }
+
+  #[test]
+  fn updates_follow_the_pool() {
+    let mut state = pool();
+    let mut jupiter_amm = jupiter_amm(&state, 0);
+
+    state.base_amount = 1_000_000_000;
+    state.quote_amount = 10_000_000;
+
+    let data = account_data(&state);
+
+    let mut account_map = AccountMap::default();
+    account_map.insert(
+      jupiter_amm.key(),
+      Account {
+        lamports: 1_000_000,
+        data,
+        owner: amm::ID,
+        executable: false,
+        rent_epoch: 0,
+      },
+    );
+
+    jupiter_amm.update(&account_map).unwrap();
+
+    assert_eq!(jupiter_amm.state.base_amount, 1_000_000_000);
+    assert_eq!(jupiter_amm.state.quote_amount, 10_000_000);
+
+    // 1_000_000 * 99 * 1_000_000_000 / (10_000_000 * 100 + 1_000_000 * 99)
+    let quote = quote(&jupiter_amm, 1_000_000, state.quote_mint, state.base_mint).unwrap();
+    assert_eq!(quote.out_amount, 90_081_892);
+  }
+
+  #[test]
+  fn inactive_pools() {
+    assert!(jupiter_amm(&pool(), 0).is_active());
+
+    for state in [
+      AmmState {
+        sunset_slot: Some(0).into(),
+        ..pool()
+      },
+      AmmState {
+        batch_window_slots: Some(10).into(),
+        ..pool()
+      },
+      AmmState {
+        gate_authority: Some(Pubkey::new_unique()).into(),
+        ..pool()
+      },
+    ] {
+      assert!(!jupiter_amm(&state, 0).is_active());
+    }
+  }
+
+  #[test]
+  fn swap_accounts() {
+    let state = pool();
+    let jupiter_amm = jupiter_amm(&state, 0);
+
+    let user = Pubkey::new_unique();
+    let user_base_account = Pubkey::new_unique();
+    let user_quote_account = Pubkey::new_unique();
+    let jupiter_program_id = Pubkey::new_unique();
+
+    let swap_params = SwapParams {
+      swap_mode: Default::default(),
+      in_amount: 1_000,
+      out_amount: 0,
+      source_mint: state.base_mint,
+      destination_mint: state.quote_mint,
+      source_token_account: user_base_account,
+      destination_token_account: user_quote_account,
+      token_transfer_authority: user,
+      open_order_address: None,
+      quote_mint_to_referrer: None,
+      jupiter_program_id: &jupiter_program_id,
+      missing_dynamic_accounts_as_default: false,
+    };
+
+    // there's no `Swap` variant to route these through
+    assert!(jupiter_amm.get_swap_and_account_metas(&swap_params).is_err());
+
+    let instruction = jupiter_amm.swap_instruction(&swap_params, 5).unwrap();
+    let account_metas = instruction.accounts;
+
+    assert_eq!(account_metas.len(), jupiter_amm.get_accounts_len());
+    assert_eq!(account_metas[0], AccountMeta::new(user, true));
+    assert_eq!(account_metas[1], AccountMeta::new(jupiter_amm.key(), false));
+    assert_eq!(account_metas[2], AccountMeta::new(user_base_account, false));
+    assert_eq!(account_metas[3], AccountMeta::new(user_quote_account, false));
+    assert_eq!(
+      account_metas[4].pubkey,
+      get_associated_token_address(&jupiter_amm.key(), &state.base_mint)
+    );
+
+    assert_eq!(instruction.program_id, amm::ID);
+    assert_eq!(
+      instruction.data,
+      amm::instruction::Swap {
+        args: SwapArgs {
+          swap_type: SwapType::Sell,
+          input_amount: 1_000,
+          output_amount_min: 5,
+          max_price_impact_bps: None,
+          limit_price: None,
+        }
+      }
+      .data()
+    );
+  }
+}
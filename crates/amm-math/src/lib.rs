@@ -0,0 +1,255 @@
+//! The math behind the amm program's pools: constant-product swaps, LP
+//! shares and the TWAP oracle.
+//!
+//! It has no dependencies, so off-chain clients can quote against exactly the
+//! same code that the program runs without pulling in Anchor or Solana.
+
+use std::fmt;
+
+pub mod liquidity;
+pub mod swap;
+pub mod twap;
+
+pub use liquidity::*;
+pub use swap::*;
+pub use twap::*;
+
+#[cfg(test)]
+mod reference;
+
+/// Prices are the number of quote units per base unit, multiplied by this.
+pub const PRICE_SCALE: u128 = 1_000_000_000_000;
+pub const MAX_PRICE: u128 = u64::MAX as u128 * PRICE_SCALE;
+pub const MAX_BPS: u16 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MathError {
+  /// One side of the pool is empty
+  NoReserves,
+  /// An intermediate product doesn't fit into 128 bits
+  InputAmountOverflow,
+  /// A result doesn't fit into its type
+  CastingOverflow,
+  /// A swap asks for at least as much as the pool holds
+  OutputExceedsReserves,
+}
+
+impl fmt::Display for MathError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let message = match self {
+      MathError::NoReserves => "the pool has no reserves on one side",
+      MathError::InputAmountOverflow => "the input amount overflows the swap math",
+      MathError::CastingOverflow => "casting has caused an overflow",
+      MathError::OutputExceedsReserves => "the output amount exceeds the pool's reserves",
+    };
+
+    f.write_str(message)
+  }
+}
+
+impl std::error::Error for MathError {}
+
+/// The price implied by the reserves, scaled by `PRICE_SCALE`. `None` if
+/// either side of the pool is empty.
+pub fn spot_price(base_amount: u64, quote_amount: u64) -> Option<u128> {
+  if base_amount == 0 || quote_amount == 0 {
+    return None;
+  }
+
+  Some((quote_amount as u128 * PRICE_SCALE) / base_amount as u128)
+}
+
+/// The square root of `n`, rounded down.
+pub fn isqrt(n: u128) -> u128 {
+  if n < 2 {
+    return n;
+  }
+
+  // start from a power of two above the root, from where Newton's method
+  // only ever steps down towards it
+  let mut x = 1u128 << (128 - n.leading_zeros()).div_ceil(2);
+
+  loop {
+    let next = (x + n / x) / 2;
+
+    if next >= x {
+      return x;
+    }
+
+    x = next;
+  }
+}
+
+/// The square root of `a * b`, rounded down to within one part in 2^62,
+/// without having to compute the product, which can need up to 256 bits.
+pub fn sqrt_of_product(a: u128, b: u128) -> u128 {
+  if a == 0 || b == 0 {
+    return 0;
+  }
+
+  // shift each factor up by an even number of bits until it has 127 or 128
+  // bits, so that its root keeps 63 or 64 of them
+  let normalized_isqrt = |n: u128| {
+    let shift = n.leading_zeros() / 2 * 2;
+
+    (isqrt(n << shift), shift / 2)
+  };
+
+  let (a_root, a_shift) = normalized_isqrt(a);
+  let (b_root, b_shift) = normalized_isqrt(b);
+
+  // both roots fit into 64 bits, so their product fits into 128
+  (a_root * b_root) >> (a_shift + b_shift)
+}
+
+/// How far `price` is from `reference_price`, in basis points of
+/// `reference_price`. `reference_price` must be non-zero.
+pub fn price_deviation_bps(reference_price: u128, price: u128) -> u128 {
+  // prices are at most `MAX_PRICE` (< 2^104), so this can't overflow
+  (reference_price.abs_diff(price) * MAX_BPS as u128) / reference_price
+}
+
+/// The largest `x` up to `max` for which `satisfies(x)` holds, where it holds
+/// for everything below any `x` that it holds for. 0 if it doesn't hold for
+/// anything, without checking 0 itself.
+///
+/// Starts at `estimate` and steps away from it in doubling steps until the
+/// answer is bracketed, then bisects. An estimate that's off by `e` costs
+/// about `2 * log2(e)` evaluations, so an exact one costs two.
+pub fn largest_satisfying(estimate: u64, max: u64, satisfies: impl Fn(u64) -> bool) -> u64 {
+  let estimate = estimate.min(max);
+  let mut step = 1u64;
+
+  // invariant: `low` satisfies it (or is 0) and `high` doesn't
+  let (mut low, mut high) = if satisfies(estimate) {
+    let mut low = estimate;
+
+    loop {
+      if low == max {
+        return max;
+      }
+
+      let next = low.saturating_add(step).min(max);
+
+      if !satisfies(next) {
+        break (low, next);
+      }
+
+      low = next;
+      step = step.saturating_mul(2);
+    }
+  } else {
+    let mut high = estimate;
+
+    loop {
+      if high == 0 {
+        return 0;
+      }
+
+      let next = high.saturating_sub(step);
+
+      if next == 0 || satisfies(next) {
+        break (next, high);
+      }
+
+      high = next;
+      step = step.saturating_mul(2);
+    }
+  };
+
+  while high - low > 1 {
+    let mid = low + (high - low) / 2;
+
+    if satisfies(mid) {
+      low = mid;
+    } else {
+      high = mid;
+    }
+  }
+
+  low
+}
+
+#[cfg(test)]
+mod math_tests {
+  use super::*;
+  use proptest::prelude::*;
+
+  #[test]
+  pub fn square_roots() {
+    for n in [0, 1, 2, 3, 4, 15, 16, 17, 1_000_000, 999_999_999_999] {
+      assert_eq!(isqrt(n), (n as f64).sqrt() as u128);
+    }
+
+    assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    assert_eq!(isqrt((u64::MAX as u128).pow(2)), u64::MAX as u128);
+    assert_eq!(isqrt((u64::MAX as u128).pow(2) - 1), u64::MAX as u128 - 1);
+  }
+
+  #[test]
+  pub fn roots_of_products() {
+    assert_eq!(sqrt_of_product(0, 5), 0);
+    assert_eq!(sqrt_of_product(4, 9), 6);
+    assert_eq!(sqrt_of_product(1 << 100, 1 << 102), 1 << 101);
+    assert!(sqrt_of_product(u128::MAX, u128::MAX) >= u128::MAX - (u128::MAX >> 62));
+
+    let product_root = sqrt_of_product(12_345_678_901, 98_765_432_109);
+    let exact_root = isqrt(12_345_678_901 * 98_765_432_109);
+    assert!(product_root <= exact_root && product_root >= exact_root - 1);
+  }
+
+  #[test]
+  pub fn largest_satisfying_from_any_estimate() {
+    for estimate in [0, 1, 41, 42, 43, 1_000, u64::MAX] {
+      assert_eq!(largest_satisfying(estimate, 1_000, |x| x <= 42), 42);
+      assert_eq!(largest_satisfying(estimate, 1_000, |x| x <= 5_000), 1_000);
+      assert_eq!(largest_satisfying(estimate, 1_000, |_| false), 0);
+    }
+
+    // an exact estimate costs two evaluations
+    let evaluations = std::cell::Cell::new(0);
+    let satisfies = |x| {
+      evaluations.set(evaluations.get() + 1);
+      x <= 42
+    };
+    assert_eq!(largest_satisfying(42, 1_000, satisfies), 42);
+    assert_eq!(evaluations.get(), 2);
+  }
+
+  #[test]
+  pub fn price_deviation() {
+    assert_eq!(price_deviation_bps(PRICE_SCALE, PRICE_SCALE), 0);
+    assert_eq!(price_deviation_bps(PRICE_SCALE, 2 * PRICE_SCALE), 10_000);
+    assert_eq!(price_deviation_bps(2 * PRICE_SCALE, PRICE_SCALE), 5_000);
+    assert_eq!(price_deviation_bps(MAX_PRICE, 0), 10_000);
+  }
+
+  proptest! {
+    #[test]
+    fn isqrt_is_the_floor_root(n: u128) {
+      let root = isqrt(n);
+
+      prop_assert!(root * root <= n);
+      prop_assert!((root + 1).checked_mul(root + 1).is_none_or(|next_square| next_square > n));
+    }
+
+    #[test]
+    fn sqrt_of_product_matches_isqrt(a: u64, b: u64) {
+      let exact_root = isqrt(a as u128 * b as u128);
+      let product_root = sqrt_of_product(a as u128, b as u128);
+
+      prop_assert!(product_root <= exact_root);
+      prop_assert!(product_root >= (exact_root - (exact_root >> 62)).saturating_sub(1));
+    }
+
+    #[test]
+    fn spot_price_matches_the_program(base_amount: u64, quote_amount: u64) {
+      prop_assert_eq!(spot_price(base_amount, quote_amount), reference::spot_price(base_amount, quote_amount));
+    }
+
+    #[test]
+    fn largest_satisfying_finds_the_threshold(threshold: u64, estimate: u64, max: u64) {
+      prop_assert_eq!(largest_satisfying(estimate, max, |x| x <= threshold), threshold.min(max));
+    }
+  }
+}
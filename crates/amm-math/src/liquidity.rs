@@ -0,0 +1,217 @@
+use crate::{isqrt, sqrt_of_product, MathError, PRICE_SCALE};
+
+/// What a deposit puts into the pool and the LP tokens it gets back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquidityDeposit {
+  pub base_amount: u64,
+  pub quote_amount: u64,
+  pub lp_tokens: u64,
+}
+
+/// Previews depositing exactly `quote_amount`, with as much base as keeps the
+/// pool's ratio. The first deposit sets the ratio, so it takes all of
+/// `max_base_amount` and gets one LP token per quote token.
+pub fn deposit_by_quote(
+  base_reserve: u64,
+  quote_reserve: u64,
+  lp_total_supply: u64,
+  quote_amount: u64,
+  max_base_amount: u64,
+) -> Result<LiquidityDeposit, MathError> {
+  if lp_total_supply == 0 {
+    return Ok(LiquidityDeposit {
+      base_amount: max_base_amount,
+      quote_amount,
+      lp_tokens: quote_amount,
+    });
+  }
+
+  if quote_reserve == 0 {
+    return Err(MathError::NoReserves);
+  }
+
+  // airlifted from uniswap v1:
+  // https://github.com/Uniswap/v1-contracts/blob/c10c08d81d6114f694baa8bd32f555a40f6264da/contracts/uniswap_exchange.vy#L48
+
+  // this should only overflow in an extreme scenario: when (quote_amount * base_reserve) / quote_reserve > u64::MAX
+  let base_amount = (((quote_amount as u128 * base_reserve as u128) / quote_reserve as u128) + 1)
+    .try_into()
+    .map_err(|_| MathError::CastingOverflow)?;
+
+  let lp_tokens = ((quote_amount as u128 * lp_total_supply as u128) / quote_reserve as u128)
+    .try_into()
+    .map_err(|_| MathError::CastingOverflow)?;
+
+  Ok(LiquidityDeposit {
+    base_amount,
+    quote_amount,
+    lp_tokens,
+  })
+}
+
+/// The mirror image of `deposit_by_quote`: previews depositing exactly
+/// `base_amount`, rounding in favor of the pool in the same way.
+pub fn deposit_by_base(
+  base_reserve: u64,
+  quote_reserve: u64,
+  lp_total_supply: u64,
+  base_amount: u64,
+  max_quote_amount: u64,
+) -> Result<LiquidityDeposit, MathError> {
+  if lp_total_supply == 0 {
+    // the first deposit always gets one LP token per quote token
+    return Ok(LiquidityDeposit {
+      base_amount,
+      quote_amount: max_quote_amount,
+      lp_tokens: max_quote_amount,
+    });
+  }
+
+  if base_reserve == 0 {
+    return Err(MathError::NoReserves);
+  }
+
+  // this should only overflow in an extreme scenario: when (base_amount * quote_reserve) / base_reserve > u64::MAX
+  let quote_amount = (((base_amount as u128 * quote_reserve as u128) / base_reserve as u128) + 1)
+    .try_into()
+    .map_err(|_| MathError::CastingOverflow)?;
+
+  let lp_tokens = ((base_amount as u128 * lp_total_supply as u128) / base_reserve as u128)
+    .try_into()
+    .map_err(|_| MathError::CastingOverflow)?;
+
+  Ok(LiquidityDeposit {
+    base_amount,
+    quote_amount,
+    lp_tokens,
+  })
+}
+
+/// How much of a `reserve` burning `lp_tokens` out of `lp_total_supply`
+/// withdraws.
+pub fn withdrawable(reserve: u64, lp_tokens: u64, lp_total_supply: u64) -> u64 {
+  // must fit back into u64 since `lp_tokens` <= `lp_total_supply`
+  ((lp_tokens as u128 * reserve as u128) / lp_total_supply as u128) as u64
+}
+
+/// Previews burning `lp_tokens`, returning the base and quote withdrawn.
+pub fn withdrawal(base_reserve: u64, quote_reserve: u64, lp_tokens: u64, lp_total_supply: u64) -> (u64, u64) {
+  (
+    withdrawable(base_reserve, lp_tokens, lp_total_supply),
+    withdrawable(quote_reserve, lp_tokens, lp_total_supply),
+  )
+}
+
+/// The square root of k per LP token, scaled by `PRICE_SCALE`. Deposits and
+/// withdrawals leave it unchanged while swap fees grow it, so its growth is
+/// what each LP token has earned in fees. `None` if there are no LP tokens.
+pub fn liquidity_per_lp_token(base_reserve: u64, quote_reserve: u64, lp_total_supply: u64) -> Option<u128> {
+  if lp_total_supply == 0 {
+    return None;
+  }
+
+  let k = base_reserve as u128 * quote_reserve as u128;
+
+  // the root of a u128 fits in 64 bits, so this can't overflow
+  Some(isqrt(k) * PRICE_SCALE / lp_total_supply as u128)
+}
+
+/// What one LP token is worth in quote units, scaled by `PRICE_SCALE`, if the
+/// pool were arbitraged to `price` while keeping its k: base of sqrt(k / P)
+/// and quote of sqrt(k * P), worth 2 * sqrt(k * P) in quote.
+pub fn fair_lp_price(base_reserve: u64, quote_reserve: u64, price: u128, lp_total_supply: u64) -> Result<u128, MathError> {
+  let k = base_reserve as u128 * quote_reserve as u128;
+
+  if lp_total_supply == 0 || k == 0 {
+    return Err(MathError::NoReserves);
+  }
+
+  // prices are scaled by 1e12, so the root of k * price is scaled by 1e6
+  let fair_quote_value = 2 * sqrt_of_product(k, price);
+
+  let fair_lp_price = fair_quote_value
+    .checked_mul(PRICE_SCALE / 1_000_000)
+    .ok_or(MathError::CastingOverflow)?
+    / lp_total_supply as u128;
+
+  Ok(fair_lp_price)
+}
+
+#[cfg(test)]
+mod liquidity_tests {
+  use super::*;
+  use crate::reference;
+  use proptest::prelude::*;
+
+  #[test]
+  pub fn first_deposit() {
+    assert_eq!(
+      deposit_by_quote(0, 0, 0, 500, 20),
+      Ok(LiquidityDeposit {
+        base_amount: 20,
+        quote_amount: 500,
+        lp_tokens: 500,
+      })
+    );
+    assert_eq!(
+      deposit_by_base(0, 0, 0, 20, 500),
+      Ok(LiquidityDeposit {
+        base_amount: 20,
+        quote_amount: 500,
+        lp_tokens: 500,
+      })
+    );
+  }
+
+  proptest! {
+    #[test]
+    fn deposits_match_the_program(
+      base_reserve in 1..=u64::MAX,
+      quote_reserve in 1..=u64::MAX,
+      lp_total_supply: u64,
+      amount: u64,
+      max_amount: u64,
+    ) {
+      prop_assert_eq!(
+        deposit_by_quote(base_reserve, quote_reserve, lp_total_supply, amount, max_amount),
+        reference::add_liquidity(base_reserve, quote_reserve, lp_total_supply, amount, max_amount)
+      );
+      prop_assert_eq!(
+        deposit_by_base(base_reserve, quote_reserve, lp_total_supply, amount, max_amount),
+        reference::add_liquidity_by_base(base_reserve, quote_reserve, lp_total_supply, amount, max_amount)
+      );
+    }
+
+    #[test]
+    fn withdrawals_match_the_program(
+      base_reserve: u64,
+      quote_reserve: u64,
+      lp_total_supply in 1..=u64::MAX,
+      lp_fraction in 0.0..=1.0f64,
+    ) {
+      let lp_tokens = (lp_total_supply as f64 * lp_fraction) as u64;
+
+      prop_assert_eq!(
+        withdrawal(base_reserve, quote_reserve, lp_tokens, lp_total_supply),
+        reference::get_base_and_quote_withdrawable(base_reserve, quote_reserve, lp_tokens, lp_total_supply)
+      );
+    }
+
+    #[test]
+    fn lp_token_values_match_the_program(
+      base_reserve: u64,
+      quote_reserve: u64,
+      price in 0..=crate::MAX_PRICE,
+      lp_total_supply: u64,
+    ) {
+      prop_assert_eq!(
+        liquidity_per_lp_token(base_reserve, quote_reserve, lp_total_supply),
+        reference::liquidity_per_lp_token(base_reserve, quote_reserve, lp_total_supply)
+      );
+      prop_assert_eq!(
+        fair_lp_price(base_reserve, quote_reserve, price, lp_total_supply),
+        reference::fair_lp_price(base_reserve, quote_reserve, price, lp_total_supply)
+      );
+    }
+  }
+}
@@ -0,0 +1,221 @@
+//! The math as the program ran it before it moved into this crate, copied
+//! out of `impl Amm` and the liquidity handlers with Anchor's errors swapped
+//! for `MathError`. The property tests check that the crate still matches it
+//! bit for bit.
+
+use crate::{isqrt, sqrt_of_product, MathError, SwapResult, MAX_BPS, PRICE_SCALE};
+use std::cmp::{max, min};
+
+macro_rules! require {
+  ($condition:expr, $error:expr) => {
+    if !$condition {
+      return Err($error);
+    }
+  };
+}
+
+pub fn spot_price(base_amount: u64, quote_amount: u64) -> Option<u128> {
+  if base_amount == 0 || quote_amount == 0 {
+    return None;
+  }
+
+  Some((quote_amount as u128 * PRICE_SCALE) / base_amount as u128)
+}
+
+pub fn swap_with_referral(
+  input_reserve: u64,
+  output_reserve: u64,
+  input_amount: u64,
+  referral_fee_bps: u16,
+) -> Result<SwapResult, MathError> {
+  let (input_reserve, output_reserve) = (input_reserve as u128, output_reserve as u128);
+
+  require!(input_reserve != 0, MathError::NoReserves);
+  require!(output_reserve != 0, MathError::NoReserves);
+
+  let input_amount_with_fee = input_amount as u128 * 99;
+
+  let numerator = input_amount_with_fee
+    .checked_mul(output_reserve)
+    .ok_or(MathError::InputAmountOverflow)?;
+
+  let denominator = (input_reserve * 100) + input_amount_with_fee;
+
+  let output_amount = (numerator / denominator).try_into().map_err(|_| MathError::CastingOverflow)?;
+
+  let fee = input_amount / 100;
+  let referral_fee = ((fee as u128 * referral_fee_bps as u128) / MAX_BPS as u128) as u64;
+  let lp_fee = fee - referral_fee;
+
+  Ok(SwapResult {
+    output_amount,
+    lp_fee,
+    referral_fee,
+  })
+}
+
+pub fn add_liquidity(
+  base_reserve: u64,
+  quote_reserve: u64,
+  total_lp_supply: u64,
+  quote_amount: u64,
+  max_base_amount: u64,
+) -> Result<crate::LiquidityDeposit, MathError> {
+  let (lp_tokens_to_mint, base_amount) = if total_lp_supply > 0 {
+    let quote_reserve = quote_reserve as u128;
+    let base_reserve = base_reserve as u128;
+
+    let base_amount: u64 = (((quote_amount as u128 * base_reserve) / quote_reserve) + 1)
+      .try_into()
+      .map_err(|_| MathError::CastingOverflow)?;
+
+    let lp_tokens_to_mint: u64 = ((quote_amount as u128 * total_lp_supply as u128) / quote_reserve)
+      .try_into()
+      .map_err(|_| MathError::CastingOverflow)?;
+
+    (lp_tokens_to_mint, base_amount)
+  } else {
+    let base_amount = max_base_amount;
+
+    let initial_lp_tokens = quote_amount;
+
+    (initial_lp_tokens, base_amount)
+  };
+
+  Ok(crate::LiquidityDeposit {
+    base_amount,
+    quote_amount,
+    lp_tokens: lp_tokens_to_mint,
+  })
+}
+
+pub fn add_liquidity_by_base(
+  base_reserve: u64,
+  quote_reserve: u64,
+  total_lp_supply: u64,
+  base_amount: u64,
+  max_quote_amount: u64,
+) -> Result<crate::LiquidityDeposit, MathError> {
+  let (lp_tokens_to_mint, quote_amount) = if total_lp_supply > 0 {
+    let quote_reserve = quote_reserve as u128;
+    let base_reserve = base_reserve as u128;
+
+    let quote_amount: u64 = (((base_amount as u128 * quote_reserve) / base_reserve) + 1)
+      .try_into()
+      .map_err(|_| MathError::CastingOverflow)?;
+
+    let lp_tokens_to_mint: u64 = ((base_amount as u128 * total_lp_supply as u128) / base_reserve)
+      .try_into()
+      .map_err(|_| MathError::CastingOverflow)?;
+
+    (lp_tokens_to_mint, quote_amount)
+  } else {
+    let quote_amount = max_quote_amount;
+
+    let initial_lp_tokens = quote_amount;
+
+    (initial_lp_tokens, quote_amount)
+  };
+
+  Ok(crate::LiquidityDeposit {
+    base_amount,
+    quote_amount,
+    lp_tokens: lp_tokens_to_mint,
+  })
+}
+
+pub fn get_base_and_quote_withdrawable(base_amount: u64, quote_amount: u64, lp_tokens: u64, lp_total_supply: u64) -> (u64, u64) {
+  (
+    ((lp_tokens as u128 * base_amount as u128) / lp_total_supply as u128) as u64,
+    ((lp_tokens as u128 * quote_amount as u128) / lp_total_supply as u128) as u64,
+  )
+}
+
+pub fn liquidity_per_lp_token(base_amount: u64, quote_amount: u64, lp_total_supply: u64) -> Option<u128> {
+  if lp_total_supply == 0 {
+    return None;
+  }
+
+  let k = base_amount as u128 * quote_amount as u128;
+
+  Some(isqrt(k) * PRICE_SCALE / lp_total_supply as u128)
+}
+
+pub fn fair_lp_price(base_amount: u64, quote_amount: u64, last_observation: u128, lp_total_supply: u64) -> Result<u128, MathError> {
+  let k = base_amount as u128 * quote_amount as u128;
+
+  require!(lp_total_supply != 0 && k != 0, MathError::NoReserves);
+
+  let fair_quote_value = 2 * sqrt_of_product(k, last_observation);
+
+  let fair_lp_price = fair_quote_value
+    .checked_mul(PRICE_SCALE / 1_000_000)
+    .ok_or(MathError::CastingOverflow)?
+    / lp_total_supply as u128;
+
+  Ok(fair_lp_price)
+}
+
+pub fn max_observation_change(last_observation: u128, max_observation_change_per_update: u128, relative: bool) -> u128 {
+  if relative {
+    max(
+      1,
+      last_observation.saturating_mul(max_observation_change_per_update) / MAX_BPS as u128,
+    )
+  } else {
+    max_observation_change_per_update
+  }
+}
+
+pub fn next_observation(last_observation: u128, price: u128, max_observation_change: u128) -> u128 {
+  if price > last_observation {
+    let max_observation = last_observation.saturating_add(max_observation_change);
+
+    min(price, max_observation)
+  } else {
+    let min_observation = last_observation.saturating_sub(max_observation_change);
+
+    max(price, min_observation)
+  }
+}
+
+pub fn accumulate(aggregator_wraps: u64, aggregator: u128, new_observation: u128, slot_difference: u64) -> (u64, u128) {
+  let (weighted_observation_wraps, weighted_observation) = wide_mul(new_observation, slot_difference);
+
+  let (new_aggregator, wrapped) = aggregator.overflowing_add(weighted_observation);
+  let new_aggregator_wraps = aggregator_wraps + weighted_observation_wraps + wrapped as u64;
+
+  (new_aggregator_wraps, new_aggregator)
+}
+
+pub fn get_twap(aggregator_wraps: u64, aggregator: u128, slots_passed: u64) -> u128 {
+  wide_div(aggregator_wraps, aggregator, slots_passed)
+}
+
+fn wide_mul(a: u128, b: u64) -> (u64, u128) {
+  let b = b as u128;
+
+  let low_product = (a & u64::MAX as u128) * b;
+  let high_product = (a >> 64) * b;
+
+  let (low, carry) = low_product.overflowing_add(high_product << 64);
+  let high = (high_product >> 64) + carry as u128;
+
+  (high as u64, low)
+}
+
+fn wide_div(high: u64, low: u128, divisor: u64) -> u128 {
+  let divisor = divisor as u128;
+
+  let mut remainder = 0u128;
+  let mut quotient = 0u128;
+
+  for word in [high as u128, low >> 64, low & u64::MAX as u128] {
+    let dividend = (remainder << 64) | word;
+
+    quotient = (quotient << 64) | (dividend / divisor);
+    remainder = dividend % divisor;
+  }
+
+  quotient
+}
@@ -0,0 +1,428 @@
+use crate::{isqrt, largest_satisfying, spot_price, sqrt_of_product, wide_div, wide_mul, MathError, MAX_BPS, PRICE_SCALE};
+
+/// How the input of a swap was split up.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapResult {
+  /// What the user gets back
+  pub output_amount: u64,
+  /// The part of the fee that stays in the pool for LPs, in the input token
+  pub lp_fee: u64,
+  /// The part of the fee that goes to the referrer instead, in the input token
+  pub referral_fee: u64,
+}
+
+impl SwapResult {
+  /// What the swap adds to the input side's reserve. The referral fee is
+  /// taken out of the input before it reaches the pool.
+  pub fn input_amount_to_pool(&self, input_amount: u64) -> u64 {
+    input_amount - self.referral_fee
+  }
+}
+
+/// Quotes swapping exactly `input_amount` into a pool holding
+/// `input_reserve` of the input token and `output_reserve` of the output
+/// token, with `referral_fee_bps` of the 1% fee going to a referrer.
+pub fn quote_exact_in(
+  input_reserve: u64,
+  output_reserve: u64,
+  input_amount: u64,
+  referral_fee_bps: u16,
+) -> Result<SwapResult, MathError> {
+  let input_reserve = input_reserve as u128;
+  let output_reserve = output_reserve as u128;
+
+  // airlifted from uniswap v1:
+  // https://github.com/Uniswap/v1-contracts/blob/c10c08d81d6114f694baa8bd32f555a40f6264da/contracts/uniswap_exchange.vy#L106-L111
+
+  if input_reserve == 0 || output_reserve == 0 {
+    return Err(MathError::NoReserves);
+  }
+
+  let input_amount_with_fee = input_amount as u128 * 99;
+
+  let numerator = input_amount_with_fee
+    .checked_mul(output_reserve)
+    .ok_or(MathError::InputAmountOverflow)?;
+
+  let denominator = (input_reserve * 100) + input_amount_with_fee;
+
+  let output_amount = (numerator / denominator).try_into().map_err(|_| MathError::CastingOverflow)?;
+
+  // the 1% fee, of which referrers can get a slice. since the fee is at most
+  // 1% of the input, the pool still gets at least the 99% that priced the swap.
+  let fee = input_amount / 100;
+  let referral_fee = referral_fee(input_amount, referral_fee_bps);
+  let lp_fee = fee - referral_fee;
+
+  Ok(SwapResult {
+    output_amount,
+    lp_fee,
+    referral_fee,
+  })
+}
+
+/// The slice of the 1% fee on `input_amount` that goes to a referrer.
+fn referral_fee(input_amount: u64, referral_fee_bps: u16) -> u64 {
+  let fee = input_amount / 100;
+
+  ((fee as u128 * referral_fee_bps as u128) / MAX_BPS as u128) as u64
+}
+
+/// The smallest input that `quote_exact_in` turns into at least
+/// `output_amount`. The referral fee doesn't change the output, so it
+/// doesn't change this either.
+pub fn quote_exact_out(input_reserve: u64, output_reserve: u64, output_amount: u64) -> Result<u64, MathError> {
+  if input_reserve == 0 || output_reserve == 0 {
+    return Err(MathError::NoReserves);
+  }
+
+  if output_amount >= output_reserve {
+    return Err(MathError::OutputExceedsReserves);
+  }
+
+  // `quote_exact_in` rounds down, so an input gets at least `output_amount`
+  // exactly when 99 * input * output_reserve >= output_amount * (100 *
+  // input_reserve + 99 * input), which rearranges to this
+  let numerator = (output_amount as u128 * 100)
+    .checked_mul(input_reserve as u128)
+    .ok_or(MathError::InputAmountOverflow)?;
+  let denominator = (output_reserve - output_amount) as u128 * 99;
+
+  let input_amount = numerator
+    .div_ceil(denominator)
+    .try_into()
+    .map_err(|_| MathError::CastingOverflow)?;
+
+  // the input still has to get through `quote_exact_in` without overflowing
+  quote_exact_in(input_reserve, output_reserve, input_amount, 0)?;
+
+  Ok(input_amount)
+}
+
+/// The largest part of `input_amount` that can be swapped while keeping the
+/// spot price at or below `limit_price` when the input is quote
+/// (`input_is_quote`), or at or above it when it's base.
+///
+/// Solved for directly, then checked against `quote_exact_in` itself, which
+/// takes a couple of evaluations when the estimate is exact.
+pub fn max_exact_in_for_price(
+  input_reserve: u64,
+  output_reserve: u64,
+  input_amount: u64,
+  limit_price: u128,
+  input_is_quote: bool,
+  referral_fee_bps: u16,
+) -> u64 {
+  let is_within_limit = |input_amount: u64| {
+    let Ok(swap_result) = quote_exact_in(input_reserve, output_reserve, input_amount, referral_fee_bps) else {
+      return false;
+    };
+
+    let Some(new_input_reserve) = input_reserve.checked_add(swap_result.input_amount_to_pool(input_amount)) else {
+      return false;
+    };
+    let new_output_reserve = output_reserve - swap_result.output_amount;
+
+    let (base_reserve, quote_reserve) = if input_is_quote {
+      (new_output_reserve, new_input_reserve)
+    } else {
+      (new_input_reserve, new_output_reserve)
+    };
+
+    match spot_price(base_reserve, quote_reserve) {
+      Some(spot_price) if input_is_quote => spot_price <= limit_price,
+      Some(spot_price) => spot_price >= limit_price,
+      None => false,
+    }
+  };
+
+  let estimate = estimate_exact_in_for_price(input_reserve, output_reserve, limit_price, input_is_quote, referral_fee_bps);
+
+  largest_satisfying(estimate, input_amount, is_within_limit)
+}
+
+/// Estimates `max_exact_in_for_price` without a limit on the input.
+///
+/// A swap of `a` grows the input reserve `x` by `c * a`, where `c` is what's
+/// left after the referral fee, and prices its output as if it were `0.99 *
+/// a`, which shrinks the output reserve to `k / (x + 0.99 * a)`. So the
+/// price reaches the limit when `(x + c * a) * (x + 0.99 * a)` reaches
+/// `t^2`, with `t` the input reserve at the limit price on a curve with the
+/// same k. That's a quadratic in `a`, solved here in a form without
+/// cancellation.
+///
+/// The output is rounded down to whole units though, which can be worth many
+/// units of input when the output reserve is much smaller than the input
+/// one. So the estimate is then redone exactly for the output that it swaps
+/// into.
+pub fn estimate_exact_in_for_price(
+  input_reserve: u64,
+  output_reserve: u64,
+  limit_price: u128,
+  input_is_quote: bool,
+  referral_fee_bps: u16,
+) -> u64 {
+  if input_reserve == 0 || output_reserve == 0 {
+    return 0;
+  }
+
+  if limit_price == 0 {
+    // nothing can be bought at no price, and anything can be sold
+    return if input_is_quote { 0 } else { u64::MAX };
+  }
+
+  let referral_fee_bps = referral_fee_bps.min(MAX_BPS);
+  let k = input_reserve as u128 * output_reserve as u128;
+
+  // the input reserve `t` at which input / output is limit_price /
+  // PRICE_SCALE when buying, or PRICE_SCALE / limit_price when selling.
+  // spot prices are rounded down, so a buy stays within the limit up to
+  // just below `limit_price + 1`.
+  let target = if input_is_quote {
+    // PRICE_SCALE is 10^12, so its root is exact
+    sqrt_of_product(k, limit_price.saturating_add(1)) / 1_000_000
+  } else {
+    // PRICE_SCALE < 2^40, so this keeps at least 62 bits for prices below 2^64
+    sqrt_of_product(k, (PRICE_SCALE << 86) / limit_price) >> 43
+  };
+
+  let x = input_reserve as u128;
+
+  if target <= x {
+    return 0;
+  }
+
+  let Ok(d) = u64::try_from(target - x) else {
+    return u64::MAX;
+  };
+
+  // c = C / M and 0.99 = H / M. the referral fee is `referral_fee_bps` of
+  // the 1% fee, so C - H is what's left of that 1% for LPs, in M units
+  const M: u128 = 1_000_000;
+  const H: u128 = 990_000;
+  let c = M - referral_fee_bps as u128;
+
+  // with t = x + d, the positive root is `2 * M * d * (t + x) / (sqrt((C -
+  // H)^2 * x^2 + 4 * C * H * t^2) + (C + H) * x)`, which is `d` times a
+  // ratio just above 1
+  //
+  // the square root is `t * sqrt(4 * C * H + w^2)` with `w = (C - H) * x /
+  // t`, which is at most 10^4, and is taken with 41 fractional bits
+  let w = (((c - H) * x) << 32) / target;
+  let root = isqrt((((4 * c * H) << 64) + w * w) << 18);
+  let discriminant_root = (target * root) >> 41;
+
+  let denominator = discriminant_root + (c + H) * x;
+  let numerator = 2 * M * (target + x);
+
+  // the ratio in 64.64 fixed point, by long division 32 bits at a time
+  let mut ratio = numerator / denominator;
+  let mut remainder = numerator % denominator;
+
+  for _ in 0..2 {
+    remainder <<= 32;
+    ratio = (ratio << 32) | (remainder / denominator);
+    remainder %= denominator;
+  }
+
+  let (high, low) = wide_mul(ratio, d);
+  let estimate = u64::try_from(((high as u128) << 64) | (low >> 64)).unwrap_or(u64::MAX);
+
+  estimate_for_output(
+    input_reserve,
+    output_reserve,
+    estimate,
+    limit_price,
+    input_is_quote,
+    referral_fee_bps,
+  )
+  .unwrap_or(estimate)
+}
+
+/// For the output `o` that `estimate` swaps into, the largest input that
+/// still swaps into `o` and keeps the price within the limit. With the
+/// output reserve fixed at `y - o`, the price only depends on the input
+/// reserve, so that can be solved for exactly.
+fn estimate_for_output(
+  input_reserve: u64,
+  output_reserve: u64,
+  estimate: u64,
+  limit_price: u128,
+  input_is_quote: bool,
+  referral_fee_bps: u16,
+) -> Option<u64> {
+  let output_amount = quote_exact_in(input_reserve, output_reserve, estimate, referral_fee_bps)
+    .ok()?
+    .output_amount;
+  let new_output_reserve = output_reserve - output_amount;
+
+  let max_new_input_reserve = if input_is_quote {
+    // floor(x' * PRICE_SCALE / y') <= limit_price, which is x' * PRICE_SCALE
+    // < (limit_price + 1) * y'
+    let (high, low) = wide_mul(limit_price.checked_add(1)?, new_output_reserve);
+    let quotient = wide_div(high, low, PRICE_SCALE as u64);
+
+    if wide_mul(quotient, PRICE_SCALE as u64) == (high, low) {
+      quotient - 1
+    } else {
+      quotient
+    }
+  } else {
+    // floor(y' * PRICE_SCALE / x') >= limit_price, which is x' <= y' *
+    // PRICE_SCALE / limit_price
+    new_output_reserve as u128 * PRICE_SCALE / limit_price
+  };
+
+  let max_input_amount_to_pool = u64::try_from(max_new_input_reserve.checked_sub(input_reserve as u128)?).ok()?;
+
+  // the referral fee is at most 1% of the input, so this starts within a
+  // unit or two of the answer
+  let max_input_amount_for_reserve = largest_satisfying(
+    (max_input_amount_to_pool as u128 * 1_000_000 / (1_000_000 - referral_fee_bps as u128)) as u64,
+    u64::MAX,
+    |input_amount| input_amount - referral_fee(input_amount, referral_fee_bps) <= max_input_amount_to_pool,
+  );
+
+  let max_input_amount_for_output = match quote_exact_out(input_reserve, output_reserve, output_amount + 1) {
+    Ok(input_amount) => input_amount - 1,
+    Err(_) => u64::MAX,
+  };
+
+  Some(max_input_amount_for_reserve.min(max_input_amount_for_output))
+}
+
+#[cfg(test)]
+mod swap_tests {
+  use super::*;
+  use crate::reference;
+  use proptest::prelude::*;
+
+  #[test]
+  pub fn exact_out() {
+    // a pool of 1,000 base and 10,000 quote
+    let input_amount = quote_exact_out(10_000, 1_000, 100).unwrap();
+
+    assert_eq!(quote_exact_in(10_000, 1_000, input_amount, 0).unwrap().output_amount, 100);
+    assert_eq!(quote_exact_in(10_000, 1_000, input_amount - 1, 0).unwrap().output_amount, 99);
+
+    assert_eq!(quote_exact_out(10_000, 1_000, 0), Ok(0));
+    assert_eq!(quote_exact_out(10_000, 1_000, 1_000), Err(MathError::OutputExceedsReserves));
+    assert_eq!(quote_exact_out(0, 1_000, 1), Err(MathError::NoReserves));
+  }
+
+  fn is_within_limit(input_reserve: u64, output_reserve: u64, input_amount: u64, limit_price: u128, input_is_quote: bool) -> bool {
+    let swap_result = quote_exact_in(input_reserve, output_reserve, input_amount, 0).unwrap();
+    let new_input_reserve = input_reserve + swap_result.input_amount_to_pool(input_amount);
+    let new_output_reserve = output_reserve - swap_result.output_amount;
+
+    if input_is_quote {
+      spot_price(new_output_reserve, new_input_reserve).unwrap() <= limit_price
+    } else {
+      spot_price(new_input_reserve, new_output_reserve).unwrap() >= limit_price
+    }
+  }
+
+  #[test]
+  pub fn exact_in_for_price() {
+    // a pool of 1,000 base and 1,000 quote, bought up to a price of 1.1
+    let input_amount = max_exact_in_for_price(1_000, 1_000, 1_000, 11 * PRICE_SCALE / 10, true, 0);
+
+    assert!(is_within_limit(1_000, 1_000, input_amount, 11 * PRICE_SCALE / 10, true));
+    assert!(!is_within_limit(1_000, 1_000, input_amount + 1, 11 * PRICE_SCALE / 10, true));
+
+    // capped by the input, and nothing when the price is already past the limit
+    assert_eq!(max_exact_in_for_price(1_000, 1_000, 10, 11 * PRICE_SCALE / 10, true, 0), 10);
+    assert_eq!(max_exact_in_for_price(1_000, 1_000, 1_000, PRICE_SCALE / 2, true, 0), 0);
+    assert_eq!(max_exact_in_for_price(1_000, 1_000, 1_000, 2 * PRICE_SCALE, false, 0), 0);
+  }
+
+  proptest! {
+    #[test]
+    fn exact_in_matches_the_program(
+      input_reserve: u64,
+      output_reserve: u64,
+      input_amount: u64,
+      referral_fee_bps in 0..=MAX_BPS,
+    ) {
+      prop_assert_eq!(
+        quote_exact_in(input_reserve, output_reserve, input_amount, referral_fee_bps),
+        reference::swap_with_referral(input_reserve, output_reserve, input_amount, referral_fee_bps)
+      );
+    }
+
+    #[test]
+    fn exact_in_matches_the_program_on_small_swaps(
+      input_reserve in 1..u32::MAX as u64,
+      output_reserve in 1..u32::MAX as u64,
+      input_amount in 0..u32::MAX as u64,
+      referral_fee_bps in 0..=MAX_BPS,
+    ) {
+      prop_assert_eq!(
+        quote_exact_in(input_reserve, output_reserve, input_amount, referral_fee_bps),
+        reference::swap_with_referral(input_reserve, output_reserve, input_amount, referral_fee_bps)
+      );
+    }
+
+    #[test]
+    fn exact_out_is_the_smallest_input(
+      input_reserve in 1..u64::MAX / 2,
+      output_reserve in 2..u64::MAX / 2,
+      output_fraction in 0.0..1.0f64,
+    ) {
+      let output_amount = ((output_reserve - 1) as f64 * output_fraction) as u64;
+
+      if let Ok(input_amount) = quote_exact_out(input_reserve, output_reserve, output_amount) {
+        let output = |input_amount| quote_exact_in(input_reserve, output_reserve, input_amount, 0).unwrap().output_amount;
+
+        prop_assert!(output(input_amount) >= output_amount);
+
+        if input_amount > 0 {
+          prop_assert!(output(input_amount - 1) < output_amount);
+        }
+      }
+    }
+
+    #[test]
+    fn exact_in_for_price_is_the_largest_within_the_limit(
+      input_reserve in 1_000..1u64 << 50,
+      output_reserve in 1_000..1u64 << 50,
+      price_move in 1.0..1.5f64,
+      input_is_quote: bool,
+      referral_fee_bps in 0..=MAX_BPS,
+    ) {
+      let (base_reserve, quote_reserve) = if input_is_quote {
+        (output_reserve, input_reserve)
+      } else {
+        (input_reserve, output_reserve)
+      };
+      let spot_price = spot_price(base_reserve, quote_reserve).unwrap() as f64;
+      let limit_price = if input_is_quote { spot_price * price_move } else { spot_price / price_move } as u128;
+      prop_assume!(limit_price > 0);
+
+      let evaluations = std::cell::Cell::new(0);
+      let is_within_limit = |input_amount| {
+        evaluations.set(evaluations.get() + 1);
+        let swap_result = quote_exact_in(input_reserve, output_reserve, input_amount, referral_fee_bps).unwrap();
+        let new_input_reserve = input_reserve + swap_result.input_amount_to_pool(input_amount);
+        let new_output_reserve = output_reserve - swap_result.output_amount;
+
+        if input_is_quote {
+          crate::spot_price(new_output_reserve, new_input_reserve).unwrap() <= limit_price
+        } else {
+          crate::spot_price(new_input_reserve, new_output_reserve).unwrap() >= limit_price
+        }
+      };
+
+      let input_amount = max_exact_in_for_price(input_reserve, output_reserve, u64::MAX / 4, limit_price, input_is_quote, referral_fee_bps);
+
+      prop_assert!(input_amount == 0 || is_within_limit(input_amount));
+      prop_assert!(!is_within_limit(input_amount + 1));
+
+      // and the estimate is close enough to only take a handful of swaps to check
+      let estimate = estimate_exact_in_for_price(input_reserve, output_reserve, limit_price, input_is_quote, referral_fee_bps);
+      evaluations.set(0);
+      prop_assert_eq!(largest_satisfying(estimate, u64::MAX / 4, is_within_limit), input_amount);
+      prop_assert!(evaluations.get() <= 8);
+    }
+  }
+}
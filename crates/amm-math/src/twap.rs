@@ -0,0 +1,216 @@
+use crate::MAX_BPS;
+use std::cmp::{max, min};
+
+/// A TWAP aggregator as of `slot`: the running sum of each observation
+/// weighted by the slots it lasted, `aggregator_wraps * 2^128 + aggregator`.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AggregatorSnapshot {
+  pub slot: u64,
+  pub aggregator: u128,
+  pub aggregator_wraps: u64,
+}
+
+impl AggregatorSnapshot {
+  /// Adds `observation`, held from this snapshot's slot until `slot`.
+  pub fn accumulate(&self, observation: u128, slot: u64) -> AggregatorSnapshot {
+    let slot_difference = slot - self.slot;
+
+    // a weighted observation can be up to 2^168, so we keep the part that
+    // doesn't fit into the aggregator as extra wraps
+    let (weighted_observation_wraps, weighted_observation) = wide_mul(observation, slot_difference);
+
+    let (aggregator, wrapped) = self.aggregator.overflowing_add(weighted_observation);
+
+    AggregatorSnapshot {
+      slot,
+      aggregator,
+      aggregator_wraps: self.aggregator_wraps + weighted_observation_wraps + wrapped as u64,
+    }
+  }
+}
+
+/// The time-weighted average price between two snapshots of the same
+/// aggregator, which lets callers average over any window that they have
+/// snapshots for. `None` if no slots passed between them.
+pub fn twap_between(start: AggregatorSnapshot, end: AggregatorSnapshot) -> Option<u128> {
+  let slots_passed = end.slot.checked_sub(start.slot).filter(|slots_passed| *slots_passed != 0)?;
+
+  // subtract the 192-bit sums, borrowing from the wraps
+  let (aggregator, borrowed) = end.aggregator.overflowing_sub(start.aggregator);
+  let aggregator_wraps = end
+    .aggregator_wraps
+    .checked_sub(start.aggregator_wraps)?
+    .checked_sub(borrowed as u64)?;
+
+  // an average of observations can't be bigger than the biggest observation,
+  // so this fits back into a u128
+  Some(wide_div(aggregator_wraps, aggregator, slots_passed))
+}
+
+/// The most that an observation can move when the limit is `bps` basis
+/// points of `last_observation`.
+pub fn max_relative_observation_change(last_observation: u128, bps: u128) -> u128 {
+  // always allow a change of at least one unit, otherwise a small enough
+  // observation could never move again
+  max(1, last_observation.saturating_mul(bps) / MAX_BPS as u128)
+}
+
+/// Moves `last_observation` towards `price`, by at most
+/// `max_observation_change`.
+pub fn next_observation(last_observation: u128, price: u128, max_observation_change: u128) -> u128 {
+  if price > last_observation {
+    let max_observation = last_observation.saturating_add(max_observation_change);
+
+    min(price, max_observation)
+  } else {
+    let min_observation = last_observation.saturating_sub(max_observation_change);
+
+    max(price, min_observation)
+  }
+}
+
+/// Multiplies `a` by `b` without overflowing, returning the product as
+/// `(high, low)` where the product is `high * 2^128 + low`.
+pub fn wide_mul(a: u128, b: u64) -> (u64, u128) {
+  let b = b as u128;
+
+  let low_product = (a & u64::MAX as u128) * b;
+  let high_product = (a >> 64) * b;
+
+  let (low, carry) = low_product.overflowing_add(high_product << 64);
+  let high = (high_product >> 64) + carry as u128;
+
+  // a 128-bit number times a 64-bit number fits into 192 bits
+  (high as u64, low)
+}
+
+/// Divides `high * 2^128 + low` by `divisor`, one 64-bit word at a time.
+pub fn wide_div(high: u64, low: u128, divisor: u64) -> u128 {
+  let divisor = divisor as u128;
+
+  let mut remainder = 0u128;
+  let mut quotient = 0u128;
+
+  for word in [high as u128, low >> 64, low & u64::MAX as u128] {
+    // `remainder` < `divisor` <= u64::MAX, so this can't overflow
+    let dividend = (remainder << 64) | word;
+
+    quotient = (quotient << 64) | (dividend / divisor);
+    remainder = dividend % divisor;
+  }
+
+  quotient
+}
+
+#[cfg(test)]
+mod twap_tests {
+  use super::*;
+  use crate::{reference, MAX_PRICE};
+  use proptest::prelude::*;
+
+  #[test]
+  pub fn windows() {
+    let start = AggregatorSnapshot::default();
+
+    let first = start.accumulate(100, 10);
+    let second = first.accumulate(400, 30);
+
+    assert_eq!(twap_between(start, first), Some(100));
+    assert_eq!(twap_between(first, second), Some(400));
+    assert_eq!(twap_between(start, second), Some(300));
+
+    assert_eq!(twap_between(first, first), None);
+    assert_eq!(twap_between(second, first), None);
+  }
+
+  #[test]
+  pub fn windows_across_wraps() {
+    let start = AggregatorSnapshot {
+      slot: 0,
+      aggregator: u128::MAX - 5,
+      aggregator_wraps: 3,
+    };
+
+    let end = start.accumulate(MAX_PRICE, 1_000);
+
+    assert_eq!(end.aggregator_wraps, 4);
+    assert_eq!(twap_between(start, end), Some(MAX_PRICE));
+  }
+
+  proptest! {
+    #[test]
+    fn observations_match_the_program(
+      last_observation in 0..=MAX_PRICE,
+      price in 0..=MAX_PRICE,
+      max_observation_change_per_update: u128,
+      relative in any::<bool>(),
+    ) {
+      let max_observation_change = if relative {
+        max_relative_observation_change(last_observation, max_observation_change_per_update)
+      } else {
+        max_observation_change_per_update
+      };
+
+      prop_assert_eq!(
+        max_observation_change,
+        reference::max_observation_change(last_observation, max_observation_change_per_update, relative)
+      );
+      prop_assert_eq!(
+        next_observation(last_observation, price, max_observation_change),
+        reference::next_observation(last_observation, price, max_observation_change)
+      );
+    }
+
+    #[test]
+    fn accumulation_matches_the_program(
+      aggregator: u128,
+      aggregator_wraps in 0..u32::MAX as u64,
+      observation in 0..=MAX_PRICE,
+      last_updated_slot in 0..u64::MAX / 2,
+      slot_difference in 0..u64::MAX / 2,
+    ) {
+      let snapshot = AggregatorSnapshot {
+        slot: last_updated_slot,
+        aggregator,
+        aggregator_wraps,
+      };
+
+      let accumulated = snapshot.accumulate(observation, last_updated_slot + slot_difference);
+
+      prop_assert_eq!(
+        (accumulated.aggregator_wraps, accumulated.aggregator),
+        reference::accumulate(aggregator_wraps, aggregator, observation, slot_difference)
+      );
+    }
+
+    #[test]
+    fn twaps_match_the_program(
+      aggregator: u128,
+      aggregator_wraps in 0..u32::MAX as u64,
+      start_slot in 0..u64::MAX / 2,
+      slots_passed in 1..u64::MAX / 2,
+    ) {
+      let start = AggregatorSnapshot {
+        slot: start_slot,
+        ..AggregatorSnapshot::default()
+      };
+      let end = AggregatorSnapshot {
+        slot: start_slot + slots_passed,
+        aggregator,
+        aggregator_wraps,
+      };
+
+      prop_assert_eq!(
+        twap_between(start, end),
+        Some(reference::get_twap(aggregator_wraps, aggregator, slots_passed))
+      );
+    }
+
+    #[test]
+    fn wide_math_round_trips(a: u128, b in 1..=u64::MAX) {
+      let (high, low) = wide_mul(a, b);
+
+      prop_assert_eq!(wide_div(high, low, b), a);
+    }
+  }
+}
@@ -1,3 +1,4 @@
+use amm_math::MathError;
 use anchor_lang::prelude::*;
 
 #[error_code]
@@ -28,7 +29,7 @@ pub enum AmmError {
   AddLiquiditySlippageExceeded,
   #[msg("LP would have spent more than `max_base_amount`")]
   AddLiquidityMaxBaseExceeded,
-  #[msg("`quote_amount` must be greater than 100000000 when initializing a pool")]
+  #[msg("`quote_amount` must be at least the pool's `min_initial_quote_amount` when initializing a pool, which defaults to one whole quote token")]
   InsufficientQuoteAmount,
   #[msg("Users must swap a non-zero amount")]
   ZeroSwapAmount,
@@ -36,4 +37,103 @@ pub enum AmmError {
   ConstantProductInvariantFailed,
   #[msg("Casting has caused an overflow")]
   CastingOverflow,
+  #[msg("A relative observation change limit needs a non-zero initial observation and basis points")]
+  InvalidRelativeObservationChange,
+  #[msg("The TWAP's end slot must be at least a minute after its start slot")]
+  InvalidTwapWindow,
+  #[msg("A swap would have moved the spot price too far from the TWAP's last observation")]
+  CircuitBreakerTriggered,
+  #[msg("The circuit breaker must allow a non-zero deviation")]
+  InvalidCircuitBreaker,
+  #[msg("The circuit breaker can only be widened or disabled")]
+  CircuitBreakerCanOnlyWiden,
+  #[msg("A swap would have had a price impact above `max_price_impact_bps`, the impact in bps is logged")]
+  PriceImpactExceeded,
+  #[msg("This token metadata account doesn't belong to the pool's base or quote mint")]
+  InvalidTokenMetadata,
+  #[msg("This pool has been sunset, it only allows removing liquidity")]
+  AmmSunset,
+  #[msg("This pool has already been sunset")]
+  AmmAlreadySunset,
+  #[msg("A pool can only be closed once every LP has withdrawn and every order has been settled")]
+  AmmNotClosable,
+  #[msg("The referral fee can't be more than the whole fee")]
+  InvalidReferralFee,
+  #[msg("The referrer's token account must hold the swap's input token")]
+  InvalidReferrerAccount,
+  #[msg("Each `batch_swap` leg needs the accounts of a `swap`, signed by the batch's user")]
+  InvalidBatchSwapAccounts,
+  #[msg("The spot price is already past `limit_price`, so nothing could be swapped")]
+  LimitPriceReached,
+  #[msg("An order needs a non-zero amount of at least the book's `min_base_amount`, a price between 0 and `MAX_PRICE`, and a quote value that fits in a u64")]
+  InvalidOrder,
+  #[msg("This side of the order book is full of orders priced at least as well, or the book owes too many makers, who anyone can settle")]
+  OrderBookFull,
+  #[msg("There's no order with this id in the book")]
+  OrderNotFound,
+  #[msg("Only an order's owner can settle or cancel it")]
+  NotOrderOwner,
+  #[msg("This pool is in batch-auction mode, swaps have to be committed to its batch queue")]
+  BatchAuctionOnly,
+  #[msg("This pool isn't in batch-auction mode")]
+  NotBatchAuction,
+  #[msg("The batch window needs to be at least one slot")]
+  InvalidBatchWindow,
+  #[msg("The current batch's window has closed, it has to be cleared before new orders come in")]
+  BatchWindowClosed,
+  #[msg("The current batch's window is still open, or it has no orders")]
+  BatchNotClearable,
+  #[msg("The current batch is full, or the batch queue owes too many users, who anyone can claim for")]
+  BatchQueueFull,
+  #[msg("A reward pool needs a non-zero emission that ends in the future, and whose total fits in a u64")]
+  InvalidRewardSchedule,
+  #[msg("Must stake or unstake a non-zero amount")]
+  ZeroStakeAmount,
+  #[msg("This pool locks LP tokens for `lp_lock_slots` after a deposit, the slot they unlock at is logged")]
+  LpPositionLocked,
+  #[msg("The LP lock needs to be at least one slot")]
+  InvalidLpLock,
+  #[msg("A locker needs a non-zero amount and an end after both its start and now")]
+  InvalidLockerSchedule,
+  #[msg("None of the locker's LP tokens have vested yet")]
+  NothingToWithdraw,
+  #[msg("This locker is already staked in a reward pool")]
+  LockerAlreadyStaked,
+  #[msg("A staked locker needs its reward pool and stake account, and an unstaked one neither")]
+  InvalidLockerRewardAccounts,
+  #[msg("This pool is gated, and the user needs an allowlist entry from its gate authority")]
+  NotAllowlisted,
+  #[msg("This pool has no gate authority")]
+  PoolNotGated,
+  #[msg("Neither of this pool's mints is the native mint, so there's no SOL to wrap")]
+  NotNativeSolPool,
+  #[msg("Wrapping SOL for a swap needs the system program")]
+  MissingSystemProgram,
+  #[msg("A swap can't take out all of a pool's reserves")]
+  OutputExceedsReserves,
+  #[msg("This pool already has the current account layout")]
+  AmmAlreadyMigrated,
+  #[msg("The referrer's token account must belong to a referrer registered with the pool, other than the user")]
+  UnregisteredReferrer,
+  #[msg("A batch order needs a non-zero input of at least the queue's minimum for its side")]
+  BatchOrderTooSmall,
+  #[msg("This pool locks new LP tokens, so deposits need the depositor's position and its escrow account")]
+  MissingLpEscrow,
+  #[msg("The user's token account is required on both sides of the pool, except on the native mint side when wrapping SOL, where it's replaced")]
+  MissingTokenAccount,
+  #[msg("LP would have spent more than `max_quote_amount`")]
+  AddLiquidityMaxQuoteExceeded,
+  #[msg("A pool can only be sunset after its TWAP's end slot, and one without an end slot can't be")]
+  SunsetBeforeTwapEnd,
+}
+
+impl From<MathError> for AmmError {
+  fn from(error: MathError) -> Self {
+    match error {
+      MathError::NoReserves => AmmError::NoReserves,
+      MathError::InputAmountOverflow => AmmError::InputAmountOverflow,
+      MathError::CastingOverflow => AmmError::CastingOverflow,
+      MathError::OutputExceedsReserves => AmmError::OutputExceedsReserves,
+    }
+  }
 }
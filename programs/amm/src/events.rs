@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{OrderSide, SwapType};
+
+#[event]
+pub struct SwapEvent {
+  pub amm: Pubkey,
+  pub user: Pubkey,
+  pub swap_type: SwapType,
+  pub input_amount: u64,
+  pub output_amount: u64,
+  /// The part of the fee kept by the pool, in the input token
+  pub lp_fee: u64,
+  /// The part of the fee paid to `referrer`, in the input token
+  pub referral_fee: u64,
+  /// The part of `input_amount` that filled against resting orders rather
+  /// than the curve, without paying the swap fee
+  pub order_book_input_amount: u64,
+  /// The referrer's token account, if there was one
+  pub referrer: Option<Pubkey>,
+}
+
+#[event]
+pub struct PlaceOrderEvent {
+  pub amm: Pubkey,
+  pub user: Pubkey,
+  pub order_id: u64,
+  pub side: OrderSide,
+  pub price: u128,
+  pub base_amount: u64,
+  /// The worst-priced order on a full side, which this one pushed off the book
+  pub evicted_order_id: Option<u64>,
+}
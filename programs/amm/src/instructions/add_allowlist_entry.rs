@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddAllowlistEntryArgs {
+  /// Who can trade and provide liquidity
+  pub user: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(args: AddAllowlistEntryArgs)]
+pub struct AddAllowlistEntry<'info> {
+  #[account(mut)]
+  pub gate_authority: Signer<'info>,
+  #[account(
+        constraint = amm.load()?.gate_authority.get() == Some(gate_authority.key()) @ AmmError::PoolNotGated,
+    )]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        init,
+        payer = gate_authority,
+        space = 8 + std::mem::size_of::<AllowlistEntry>(),
+        seeds = [
+            ALLOWLIST_ENTRY_SEED_PREFIX,
+            amm.key().as_ref(),
+            args.user.as_ref()
+        ],
+        bump
+    )]
+  pub allowlist_entry: Account<'info, AllowlistEntry>,
+  pub system_program: Program<'info, System>,
+}
+
+impl AddAllowlistEntry<'_> {
+  pub fn handle(ctx: Context<Self>, args: AddAllowlistEntryArgs) -> Result<()> {
+    let AddAllowlistEntry {
+      gate_authority: _,
+      amm,
+      allowlist_entry,
+      system_program: _,
+    } = ctx.accounts;
+
+    allowlist_entry.set_inner(AllowlistEntry {
+      bump: ctx.bumps.allowlist_entry,
+      amm: amm.key(),
+      user: args.user,
+    });
+
+    Ok(())
+  }
+}
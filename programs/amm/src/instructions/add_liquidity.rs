@@ -1,9 +1,10 @@
+use amm_math::LiquidityDeposit;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, *};
 
 use crate::error::AmmError;
-use crate::AddOrRemoveLiquidity;
 use crate::{generate_amm_seeds, state::*};
+use crate::{user_token_accounts, AddOrRemoveLiquidity};
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct AddLiquidityArgs {
@@ -15,19 +16,24 @@ pub struct AddLiquidityArgs {
   min_lp_tokens: u64,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddLiquidityByBaseArgs {
+  /// How much base token you will deposit to the pool
+  pub base_amount: u64,
+  /// The maximum quote token you will deposit to the pool
+  pub max_quote_amount: u64,
+  /// The minimum LP token you will get back
+  pub min_lp_tokens: u64,
+}
+
 impl AddOrRemoveLiquidity<'_> {
   pub fn handle_add(ctx: Context<Self>, args: AddLiquidityArgs) -> Result<()> {
-    let AddOrRemoveLiquidity {
-      user,
-      amm,
-      lp_mint,
-      user_lp_account,
-      user_base_account,
-      user_quote_account,
-      vault_ata_base,
-      vault_ata_quote,
-      token_program,
-    } = ctx.accounts;
+    ctx.accounts.wrap_native_sol(args.max_base_amount, args.quote_amount)?;
+
+    let (user_base_account, user_quote_account) = ctx.accounts.user_token_accounts()?;
+    let (user_base_amount, user_quote_amount) = (user_base_account.amount, user_quote_account.amount);
+
+    let AddOrRemoveLiquidity { amm, lp_mint, .. } = &mut *ctx.accounts;
 
     let AddLiquidityArgs {
       quote_amount,
@@ -35,53 +41,161 @@ impl AddOrRemoveLiquidity<'_> {
       min_lp_tokens,
     } = args;
 
-    require_gte!(user_base_account.amount, max_base_amount, AmmError::InsufficientBalance);
-    require_gte!(user_quote_account.amount, quote_amount, AmmError::InsufficientBalance);
+    require_gte!(user_base_amount, max_base_amount, AmmError::InsufficientBalance);
+    require_gte!(user_quote_amount, quote_amount, AmmError::InsufficientBalance);
 
-    amm.update_twap(Clock::get()?.slot);
+    let mut amm = amm.load_mut()?;
 
-    // airlifted from uniswap v1:
-    // https://github.com/Uniswap/v1-contracts/blob/c10c08d81d6114f694baa8bd32f555a40f6264da/contracts/uniswap_exchange.vy#L48
+    amm.update_twap(Clock::get()?.slot);
 
     require!(max_base_amount > 0, AmmError::ZeroLiquidityToAdd);
     require!(quote_amount > 0, AmmError::ZeroLiquidityToAdd);
+    require!(!amm.is_sunset(), AmmError::AmmSunset);
 
     let total_lp_supply = lp_mint.supply;
 
-    let (lp_tokens_to_mint, base_amount) = if total_lp_supply > 0 {
+    let LiquidityDeposit {
+      base_amount,
+      lp_tokens: lp_tokens_to_mint,
+      ..
+    } = amm_math::deposit_by_quote(
+      amm.base_amount,
+      amm.quote_amount,
+      total_lp_supply,
+      quote_amount,
+      max_base_amount,
+    )
+    .map_err(AmmError::from)?;
+
+    if total_lp_supply > 0 {
       require!(min_lp_tokens > 0, AmmError::ZeroMinLpTokens);
+      require_gte!(max_base_amount, base_amount, AmmError::AddLiquidityMaxBaseExceeded);
+      require_gte!(lp_tokens_to_mint, min_lp_tokens, AmmError::AddLiquiditySlippageExceeded);
+    } else {
+      require_gte!(quote_amount, amm.min_initial_quote_amount, AmmError::InsufficientQuoteAmount);
+    }
 
-      let quote_reserve = amm.quote_amount as u128;
-      let base_reserve = amm.base_amount as u128;
+    drop(amm);
 
-      // this should only panic in an extreme scenario: when (quote_amount * base_reserve) / quote_reserve > u64::MAX
-      let base_amount: u64 = (((quote_amount as u128 * base_reserve) / quote_reserve) + 1)
-        .try_into()
-        .map_err(|_| AmmError::CastingOverflow)?;
+    ctx
+      .accounts
+      .deposit(base_amount, quote_amount, lp_tokens_to_mint, ctx.bumps.lp_position)?;
+    ctx.accounts.unwrap_native_sol()
+  }
 
-      let lp_tokens_to_mint: u64 = ((quote_amount as u128 * total_lp_supply as u128) / quote_reserve)
-        .try_into()
-        .map_err(|_| AmmError::CastingOverflow)?;
+  pub fn handle_add_by_base(ctx: Context<Self>, args: AddLiquidityByBaseArgs) -> Result<()> {
+    ctx.accounts.wrap_native_sol(args.base_amount, args.max_quote_amount)?;
 
-      require_gte!(max_base_amount, base_amount, AmmError::AddLiquidityMaxBaseExceeded);
-      require_gte!(lp_tokens_to_mint, min_lp_tokens, AmmError::AddLiquiditySlippageExceeded);
+    let (user_base_account, user_quote_account) = ctx.accounts.user_token_accounts()?;
+    let (user_base_amount, user_quote_amount) = (user_base_account.amount, user_quote_account.amount);
+
+    let AddOrRemoveLiquidity { amm, lp_mint, .. } = &mut *ctx.accounts;
+
+    let AddLiquidityByBaseArgs {
+      base_amount,
+      max_quote_amount,
+      min_lp_tokens,
+    } = args;
+
+    require_gte!(user_base_amount, base_amount, AmmError::InsufficientBalance);
+    require_gte!(user_quote_amount, max_quote_amount, AmmError::InsufficientBalance);
 
-      (lp_tokens_to_mint, base_amount)
+    let mut amm = amm.load_mut()?;
+
+    amm.update_twap(Clock::get()?.slot);
+
+    require!(base_amount > 0, AmmError::ZeroLiquidityToAdd);
+    require!(max_quote_amount > 0, AmmError::ZeroLiquidityToAdd);
+    require!(!amm.is_sunset(), AmmError::AmmSunset);
+
+    let total_lp_supply = lp_mint.supply;
+
+    let LiquidityDeposit {
+      quote_amount,
+      lp_tokens: lp_tokens_to_mint,
+      ..
+    } = amm_math::deposit_by_base(
+      amm.base_amount,
+      amm.quote_amount,
+      total_lp_supply,
+      base_amount,
+      max_quote_amount,
+    )
+    .map_err(AmmError::from)?;
+
+    if total_lp_supply > 0 {
+      require!(min_lp_tokens > 0, AmmError::ZeroMinLpTokens);
+      require_gte!(max_quote_amount, quote_amount, AmmError::AddLiquidityMaxQuoteExceeded);
+      require_gte!(lp_tokens_to_mint, min_lp_tokens, AmmError::AddLiquiditySlippageExceeded);
     } else {
-      // equivalent to $100 if quote is USDC, here for rounding
-      require_gte!(quote_amount, 100000000, AmmError::InsufficientQuoteAmount);
+      require_gte!(quote_amount, amm.min_initial_quote_amount, AmmError::InsufficientQuoteAmount);
+    }
 
-      let base_amount = max_base_amount;
+    drop(amm);
 
-      let initial_lp_tokens = quote_amount;
+    ctx
+      .accounts
+      .deposit(base_amount, quote_amount, lp_tokens_to_mint, ctx.bumps.lp_position)?;
+    ctx.accounts.unwrap_native_sol()
+  }
 
-      (initial_lp_tokens, base_amount)
-    };
+  /// Moves the deposit into the pool's vaults and mints the LP tokens for it,
+  /// into the position's escrow if the pool locks liquidity.
+  fn deposit(&mut self, base_amount: u64, quote_amount: u64, lp_tokens_to_mint: u64, lp_position_bump: u8) -> Result<()> {
+    let AddOrRemoveLiquidity {
+      user,
+      amm: amm_account,
+      lp_mint,
+      user_lp_account,
+      user_base_account,
+      user_quote_account,
+      vault_ata_base,
+      vault_ata_quote,
+      lp_position,
+      lp_escrow,
+      allowlist_entry,
+      native_mint: _,
+      native_sol_account,
+      associated_token_program: _,
+      token_program,
+      system_program: _,
+    } = self;
+
+    let mut amm = amm_account.load_mut()?;
+
+    let (user_base_account, user_quote_account) =
+      user_token_accounts(&amm, user_base_account, user_quote_account, native_sol_account)?;
+
+    amm.check_allowlisted(
+      amm_account.key(),
+      user.key(),
+      allowlist_entry.as_deref().map(|entry| &**entry),
+    )?;
 
     amm.base_amount += base_amount;
     amm.quote_amount += quote_amount;
 
-    let seeds = generate_amm_seeds!(amm);
+    let lp_destination = if amm.lp_lock_slots.get().is_some() {
+      let (Some(lp_position), Some(lp_escrow)) = (lp_position, lp_escrow) else {
+        return err!(AmmError::MissingLpEscrow);
+      };
+
+      // a new deposit relocks everything in the escrow
+      lp_position.amm = amm_account.key();
+      lp_position.owner = user.key();
+      lp_position.deposit_slot = Clock::get()?.slot;
+      lp_position.bump = lp_position_bump;
+
+      lp_escrow.to_account_info()
+    } else {
+      user_lp_account.to_account_info()
+    };
+
+    // the pool can't sign with its data borrowed
+    let amm_signer = amm.signer();
+    drop(amm);
+
+    let seeds = generate_amm_seeds!(amm_signer);
     let signer = &[&seeds[..]];
 
     token::mint_to(
@@ -89,8 +203,8 @@ impl AddOrRemoveLiquidity<'_> {
         token_program.to_account_info(),
         MintTo {
           mint: lp_mint.to_account_info(),
-          to: user_lp_account.to_account_info(),
-          authority: amm.to_account_info(),
+          to: lp_destination,
+          authority: amm_account.to_account_info(),
         },
         signer,
       ),
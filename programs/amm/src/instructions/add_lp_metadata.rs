@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+use anchor_spl::metadata::{
+  create_metadata_accounts_v3,
+  mpl_token_metadata::{types::DataV2, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH},
+  CreateMetadataAccountsV3, Metadata, MetadataAccount,
+};
+use anchor_spl::token::Mint;
+
+use crate::error::AmmError;
+use crate::{generate_amm_seeds, state::*};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddLpMetadataArgs {
+  pub uri: String,
+}
+
+#[derive(Accounts)]
+pub struct AddLpMetadata<'info> {
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  /// Only the admin can pick the URI, since nobody can change it afterwards
+  pub admin: Signer<'info>,
+  #[account(
+        has_one = admin,
+        has_one = lp_mint,
+    )]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(mut)]
+  pub lp_mint: Box<Account<'info, Mint>>,
+  #[account(
+        constraint = base_token_metadata.mint == amm.load()?.base_mint @ AmmError::InvalidTokenMetadata
+    )]
+  pub base_token_metadata: Box<Account<'info, MetadataAccount>>,
+  #[account(
+        constraint = quote_token_metadata.mint == amm.load()?.quote_mint @ AmmError::InvalidTokenMetadata
+    )]
+  pub quote_token_metadata: Box<Account<'info, MetadataAccount>>,
+  /// CHECK: verified via cpi into token metadata
+  #[account(mut)]
+  pub lp_token_metadata: AccountInfo<'info>,
+  pub token_metadata_program: Program<'info, Metadata>,
+  pub system_program: Program<'info, System>,
+  pub rent: Sysvar<'info, Rent>,
+}
+
+impl AddLpMetadata<'_> {
+  pub fn handle(ctx: Context<Self>, args: AddLpMetadataArgs) -> Result<()> {
+    let AddLpMetadata {
+      payer,
+      admin: _,
+      amm,
+      lp_mint,
+      base_token_metadata,
+      quote_token_metadata,
+      lp_token_metadata,
+      token_metadata_program,
+      system_program,
+      rent,
+    } = ctx.accounts;
+
+    let amm_signer = amm.load()?.signer();
+    let seeds = generate_amm_seeds!(amm_signer);
+    let signer_seeds = &[&seeds[..]];
+
+    // there are null bytes we must trim from string, otherwise string value is longer than we want
+    let base_token_symbol = base_token_metadata.symbol.trim_matches(char::from(0));
+    let quote_token_symbol = quote_token_metadata.symbol.trim_matches(char::from(0));
+
+    // e.g. "pMETA/pUSDC LP" and "pMETA-LP"
+    let name = truncate(format!("{}/{} LP", base_token_symbol, quote_token_symbol), MAX_NAME_LENGTH);
+    let symbol = truncate(format!("{}-LP", base_token_symbol), MAX_SYMBOL_LENGTH);
+
+    let cpi_accounts = CreateMetadataAccountsV3 {
+      metadata: lp_token_metadata.to_account_info(),
+      mint: lp_mint.to_account_info(),
+      mint_authority: amm.to_account_info(),
+      payer: payer.to_account_info(),
+      update_authority: amm.to_account_info(),
+      system_program: system_program.to_account_info(),
+      rent: rent.to_account_info(),
+    };
+
+    create_metadata_accounts_v3(
+      CpiContext::new(token_metadata_program.to_account_info(), cpi_accounts).with_signer(signer_seeds),
+      DataV2 {
+        name,
+        symbol,
+        uri: args.uri,
+        seller_fee_basis_points: 0,
+        creators: None,
+        collection: None,
+        uses: None,
+      },
+      false,
+      true,
+      None,
+    )?;
+
+    Ok(())
+  }
+}
+
+/// Cuts `string` down to at most `max_len` bytes without splitting a character.
+fn truncate(mut string: String, max_len: usize) -> String {
+  let mut len = string.len().min(max_len);
+
+  while !string.is_char_boundary(len) {
+    len -= 1;
+  }
+
+  string.truncate(len);
+  string
+}
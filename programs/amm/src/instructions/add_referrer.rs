@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddReferrerArgs {
+  /// Who the referral fees can be paid to
+  pub owner: Pubkey,
+}
+
+#[derive(Accounts)]
+#[instruction(args: AddReferrerArgs)]
+pub struct AddReferrer<'info> {
+  #[account(mut)]
+  pub admin: Signer<'info>,
+  #[account(has_one = admin)]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        init,
+        payer = admin,
+        space = 8 + std::mem::size_of::<Referrer>(),
+        seeds = [
+            REFERRER_SEED_PREFIX,
+            amm.key().as_ref(),
+            args.owner.as_ref()
+        ],
+        bump
+    )]
+  pub referrer: Account<'info, Referrer>,
+  pub system_program: Program<'info, System>,
+}
+
+impl AddReferrer<'_> {
+  pub fn handle(ctx: Context<Self>, args: AddReferrerArgs) -> Result<()> {
+    let AddReferrer {
+      admin: _,
+      amm,
+      referrer,
+      system_program: _,
+    } = ctx.accounts;
+
+    referrer.set_inner(Referrer {
+      bump: ctx.bumps.referrer,
+      amm: amm.key(),
+      owner: args.owner,
+    });
+
+    Ok(())
+  }
+}
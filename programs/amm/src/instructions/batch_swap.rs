@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+use std::collections::BTreeSet;
+
+use crate::error::AmmError;
+use crate::{Swap, SwapArgs, SwapBumps};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct BatchSwapArgs {
+  /// One entry per leg, each with its own slippage limits
+  pub swaps: Vec<SwapArgs>,
+}
+
+/// Runs several independent swaps in one instruction. Each leg's accounts are
+/// passed through `remaining_accounts`, laid out exactly like the accounts of
+/// `swap`, and go through the same checks. If any leg fails, they all do.
+#[derive(Accounts)]
+pub struct BatchSwap<'info> {
+  pub user: Signer<'info>,
+}
+
+impl<'info> BatchSwap<'info> {
+  pub fn handle(ctx: Context<'_, '_, 'info, 'info, Self>, args: BatchSwapArgs) -> Result<()> {
+    let BatchSwap { user } = ctx.accounts;
+
+    let mut remaining_accounts = ctx.remaining_accounts;
+
+    for swap_args in args.swaps {
+      let mut swap = Swap::try_accounts(
+        ctx.program_id,
+        &mut remaining_accounts,
+        &[],
+        &mut SwapBumps::default(),
+        &mut BTreeSet::new(),
+      )?;
+
+      require_keys_eq!(swap.user.key(), user.key(), AmmError::InvalidBatchSwapAccounts);
+
+      swap.execute(swap_args)?;
+
+      // persist this leg's order book before the next leg reads it, the
+      // `Amm` itself is written in place
+      swap.exit(ctx.program_id)?;
+    }
+
+    require!(remaining_accounts.is_empty(), AmmError::InvalidBatchSwapAccounts);
+
+    Ok(())
+  }
+}
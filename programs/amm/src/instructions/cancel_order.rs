@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+use crate::*;
+
+#[derive(Debug, Clone, Copy, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
+pub struct CancelOrderArgs {
+  pub order_id: u64,
+}
+
+impl ManageOrder<'_> {
+  /// Also pays out anything else the book owes the user. Works on sunset
+  /// pools too, so makers can get their escrow back before the pool is closed.
+  pub fn handle_cancel(ctx: Context<Self>, args: CancelOrderArgs) -> Result<()> {
+    let maker_count = ctx.accounts.order_book.maker_count();
+    let (base_amount, quote_amount) = ctx.accounts.order_book.cancel(ctx.accounts.user.key(), args.order_id)?;
+    ctx.accounts.amm.load_mut()?.open_orders -= maker_count - ctx.accounts.order_book.maker_count();
+
+    ctx.accounts.pay_out(base_amount, quote_amount)
+  }
+}
@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::*;
+
+/// Anyone can claim for a user, since the tokens can only go to the user's
+/// own accounts. That's what frees up the balances of users without orders.
+#[derive(Accounts)]
+pub struct ClaimBatchOrders<'info> {
+  #[account(mut)]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        mut,
+        has_one = amm,
+    )]
+  pub batch_queue: Box<Account<'info, BatchQueue>>,
+  /// CHECK: only used as the authority of the token accounts paid out to
+  pub owner: UncheckedAccount<'info>,
+  #[account(
+        mut,
+        token::mint = amm.load()?.base_mint,
+        token::authority = owner,
+    )]
+  pub owner_base_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = amm.load()?.quote_mint,
+        token::authority = owner,
+    )]
+  pub owner_quote_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.base_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_base: Account<'info, TokenAccount>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.quote_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_quote: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+}
+
+impl ClaimBatchOrders<'_> {
+  /// Pays out what a user's cleared orders are owed. Works on sunset pools too.
+  pub fn handle(ctx: Context<Self>) -> Result<()> {
+    let ClaimBatchOrders {
+      amm,
+      batch_queue,
+      owner,
+      owner_base_account,
+      owner_quote_account,
+      vault_ata_base,
+      vault_ata_quote,
+      token_program,
+    } = ctx.accounts;
+
+    let user_count = batch_queue.user_count();
+    let (base_amount, quote_amount) = batch_queue.claim(owner.key());
+    amm.load_mut()?.open_orders -= user_count - batch_queue.user_count();
+
+    pay_out_from_vaults(
+      amm,
+      token_program,
+      [
+        (base_amount, vault_ata_base, owner_base_account),
+        (quote_amount, vault_ata_quote, owner_quote_account),
+      ],
+    )
+  }
+}
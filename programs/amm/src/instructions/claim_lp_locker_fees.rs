@@ -0,0 +1,95 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Burn, Transfer};
+
+use crate::generate_lp_locker_seeds;
+use crate::*;
+
+impl ManageLpLocker<'_> {
+  /// Pays out the swap fees that the locked LP tokens have earned since the
+  /// last claim, by withdrawing just enough of them that what stays locked
+  /// still has the liquidity it had then.
+  pub fn handle_claim_fees(ctx: Context<Self>) -> Result<()> {
+    let lp_total_supply = ctx.accounts.lp_mint.supply;
+    let Some(liquidity_per_lp_token) = ctx.accounts.amm.load()?.liquidity_per_lp_token(lp_total_supply) else {
+      return Ok(());
+    };
+
+    let lp_tokens_to_burn = ctx.accounts.lp_locker.fee_lp_tokens(liquidity_per_lp_token);
+
+    ctx.accounts.unstake_from_reward_pool(lp_tokens_to_burn)?;
+
+    let ManageLpLocker {
+      owner: _,
+      amm: amm_account,
+      lp_mint,
+      lp_locker,
+      locker_vault,
+      owner_lp_account: _,
+      owner_base_account,
+      owner_quote_account,
+      vault_ata_base,
+      vault_ata_quote,
+      reward_pool: _,
+      stake_account: _,
+      token_program,
+    } = ctx.accounts;
+
+    lp_locker.liquidity_per_lp_token_claimed = lp_locker.liquidity_per_lp_token_claimed.max(liquidity_per_lp_token);
+
+    if lp_tokens_to_burn == 0 {
+      return Ok(());
+    }
+
+    let mut amm = amm_account.load_mut()?;
+
+    amm.update_twap(Clock::get()?.slot);
+
+    let (base_to_withdraw, quote_to_withdraw) = amm.get_base_and_quote_withdrawable(lp_tokens_to_burn, lp_total_supply);
+
+    lp_locker.total_amount -= lp_tokens_to_burn;
+    amm.locked_lp_tokens -= lp_tokens_to_burn;
+    amm.base_amount -= base_to_withdraw;
+    amm.quote_amount -= quote_to_withdraw;
+
+    // the pool can't sign with its data borrowed
+    let amm_signer = amm.signer();
+    drop(amm);
+
+    let locker_seeds = generate_lp_locker_seeds!(lp_locker);
+
+    token::burn(
+      CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Burn {
+          mint: lp_mint.to_account_info(),
+          from: locker_vault.to_account_info(),
+          authority: lp_locker.to_account_info(),
+        },
+        &[locker_seeds],
+      ),
+      lp_tokens_to_burn,
+    )?;
+
+    let seeds = generate_amm_seeds!(amm_signer);
+
+    for (amount_to_withdraw, from, to) in [
+      (base_to_withdraw, vault_ata_base, owner_base_account),
+      (quote_to_withdraw, vault_ata_quote, owner_quote_account),
+    ] {
+      token::transfer(
+        CpiContext::new_with_signer(
+          token_program.to_account_info(),
+          Transfer {
+            from: from.to_account_info(),
+            to: to.to_account_info(),
+            authority: amm_account.to_account_info(),
+          },
+          &[seeds],
+        ),
+        amount_to_withdraw,
+      )?;
+    }
+
+    Ok(())
+  }
+}
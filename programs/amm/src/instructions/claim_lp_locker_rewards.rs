@@ -0,0 +1,76 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, *};
+
+use crate::error::AmmError;
+use crate::generate_reward_pool_seeds;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct ClaimLpLockerRewards<'info> {
+  pub owner: Signer<'info>,
+  #[account(has_one = owner)]
+  pub lp_locker: Box<Account<'info, LpLocker>>,
+  #[account(
+        mut,
+        constraint = lp_locker.reward_pool == Some(reward_pool.key()) @ AmmError::InvalidLockerRewardAccounts,
+    )]
+  pub reward_pool: Box<Account<'info, RewardPool>>,
+  #[account(
+        mut,
+        seeds = [
+            STAKE_ACCOUNT_SEED_PREFIX,
+            reward_pool.key().as_ref(),
+            lp_locker.key().as_ref()
+        ],
+        bump = stake_account.bump
+    )]
+  pub stake_account: Box<Account<'info, StakeAccount>>,
+  #[account(
+        mut,
+        associated_token::mint = reward_pool.reward_mint,
+        associated_token::authority = reward_pool,
+    )]
+  pub reward_vault: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = reward_pool.reward_mint,
+        token::authority = owner,
+    )]
+  pub owner_reward_account: Box<Account<'info, TokenAccount>>,
+  pub token_program: Program<'info, Token>,
+}
+
+impl ClaimLpLockerRewards<'_> {
+  pub fn handle(ctx: Context<Self>) -> Result<()> {
+    let ClaimLpLockerRewards {
+      owner: _,
+      lp_locker: _,
+      reward_pool,
+      stake_account,
+      reward_vault,
+      owner_reward_account,
+      token_program,
+    } = ctx.accounts;
+
+    let claimed = reward_pool.claim(stake_account, reward_vault.amount, Clock::get()?.slot)?;
+
+    if claimed == 0 {
+      return Ok(());
+    }
+
+    let seeds = generate_reward_pool_seeds!(reward_pool);
+
+    token::transfer(
+      CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Transfer {
+          from: reward_vault.to_account_info(),
+          to: owner_reward_account.to_account_info(),
+          authority: reward_pool.to_account_info(),
+        },
+        &[seeds],
+      ),
+      claimed,
+    )
+  }
+}
@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+use crate::*;
+
+impl ManageStake<'_> {
+  pub fn handle_claim(ctx: Context<Self>) -> Result<()> {
+    let accounts = ctx.accounts;
+
+    let claimed = accounts
+      .reward_pool
+      .claim(&mut accounts.stake_account, accounts.reward_vault.amount, Clock::get()?.slot)?;
+
+    accounts.pay_out(&accounts.reward_vault, &accounts.user_reward_account, claimed)
+  }
+}
@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct ClearBatch<'info> {
+  #[account(mut)]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        mut,
+        has_one = amm,
+    )]
+  pub batch_queue: Box<Account<'info, BatchQueue>>,
+}
+
+impl ClearBatch<'_> {
+  /// Permissionless. Doesn't check the circuit breaker, since a batch that
+  /// can't clear would hold its orders' tokens until the pool is closed.
+  pub fn handle(ctx: Context<Self>) -> Result<()> {
+    let ClearBatch { amm, batch_queue } = ctx.accounts;
+
+    let mut amm = amm.load_mut()?;
+
+    let Some(batch_window_slots) = amm.batch_window_slots.get() else {
+      return err!(AmmError::NotBatchAuction);
+    };
+
+    let current_slot = Clock::get()?.slot;
+
+    require!(
+      batch_queue.has_pending_orders() && current_slot >= batch_queue.window_start_slot + batch_window_slots,
+      AmmError::BatchNotClearable
+    );
+
+    if let Some(clearing_price) = batch_queue.clear(&mut amm, current_slot)? {
+      msg!("Cleared at {}", clearing_price);
+
+      amm.last_clearing_price = Some(clearing_price).into();
+    }
+
+    amm.update_twap(current_slot);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,133 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, spl_token::instruction::AuthorityType, *};
+
+use crate::error::AmmError;
+use crate::{generate_amm_seeds, state::*};
+
+#[derive(Accounts)]
+pub struct CloseAmm<'info> {
+  pub admin: Signer<'info>,
+  /// CHECK: only receives the rent of the closed accounts
+  #[account(mut)]
+  pub recipient: UncheckedAccount<'info>,
+  #[account(
+        mut,
+        has_one = admin,
+        has_one = lp_mint,
+        close = recipient,
+    )]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(mut)]
+  pub lp_mint: Box<Account<'info, Mint>>,
+  #[account(
+        mut,
+        address = amm.load()?.base_mint,
+    )]
+  pub base_mint: Box<Account<'info, Mint>>,
+  #[account(
+        mut,
+        address = amm.load()?.quote_mint,
+    )]
+  pub quote_mint: Box<Account<'info, Mint>>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.base_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_base: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.quote_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_quote: Box<Account<'info, TokenAccount>>,
+  pub token_program: Program<'info, Token>,
+}
+
+impl CloseAmm<'_> {
+  /// Sunset pools don't get a way around this. Whatever their LPs haven't
+  /// withdrawn is still theirs, and closing the pool would burn it, so a
+  /// sunset pool closes once its LPs have wound it down like any other.
+  pub fn validate(&self) -> Result<()> {
+    let amm = self.amm.load()?;
+
+    // every token in the vaults that belongs to someone is either backing LP
+    // tokens, locked ones included, or escrowed for an open order
+    let is_drained = self.lp_mint.supply == 0
+      && amm.locked_lp_tokens == 0
+      && amm.open_orders == 0
+      && amm.base_amount == 0
+      && amm.quote_amount == 0;
+
+    require!(is_drained, AmmError::AmmNotClosable);
+
+    Ok(())
+  }
+
+  pub fn handle(ctx: Context<Self>) -> Result<()> {
+    let CloseAmm {
+      admin: _,
+      recipient,
+      amm,
+      lp_mint,
+      base_mint,
+      quote_mint,
+      vault_ata_base,
+      vault_ata_quote,
+      token_program,
+    } = ctx.accounts;
+
+    let amm_signer = amm.load()?.signer();
+    let seeds = generate_amm_seeds!(amm_signer);
+    let signer = &[&seeds[..]];
+
+    for (vault_ata, mint) in [(vault_ata_base, base_mint), (vault_ata_quote, quote_mint)] {
+      // nobody has a claim on what's left, which is only rounding dust and
+      // donations
+      if vault_ata.amount > 0 {
+        token::burn(
+          CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Burn {
+              mint: mint.to_account_info(),
+              from: vault_ata.to_account_info(),
+              authority: amm.to_account_info(),
+            },
+            signer,
+          ),
+          vault_ata.amount,
+        )?;
+      }
+
+      token::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+          account: vault_ata.to_account_info(),
+          destination: recipient.to_account_info(),
+          authority: amm.to_account_info(),
+        },
+        signer,
+      ))?;
+    }
+
+    // the LP mint outlives the pool, so make sure nobody can mint or freeze
+    // LP tokens with it again. it also keeps its address taken, so a closed
+    // pool's mints can't be paired up again.
+    for authority_type in [AuthorityType::MintTokens, AuthorityType::FreezeAccount] {
+      token::set_authority(
+        CpiContext::new_with_signer(
+          token_program.to_account_info(),
+          SetAuthority {
+            current_authority: amm.to_account_info(),
+            account_or_mint: lp_mint.to_account_info(),
+          },
+          signer,
+        ),
+        authority_type,
+        None,
+      )?;
+    }
+
+    Ok(())
+  }
+}
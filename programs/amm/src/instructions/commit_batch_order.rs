@@ -0,0 +1,91 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+
+use crate::error::AmmError;
+use crate::*;
+
+#[derive(Debug, Clone, Copy, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
+pub struct CommitBatchOrderArgs {
+  pub swap_type: SwapType,
+  pub input_amount: u64,
+  /// The least the order has to get when its batch clears, or it's refunded
+  pub min_output_amount: u64,
+}
+
+impl ManageBatchOrder<'_> {
+  pub fn handle_commit(ctx: Context<Self>, args: CommitBatchOrderArgs) -> Result<()> {
+    let ManageBatchOrder {
+      user,
+      amm: amm_account,
+      batch_queue,
+      user_base_account,
+      user_quote_account,
+      vault_ata_base,
+      vault_ata_quote,
+      allowlist_entry,
+      token_program,
+    } = ctx.accounts;
+
+    let CommitBatchOrderArgs {
+      swap_type,
+      input_amount,
+      min_output_amount,
+    } = args;
+
+    let mut amm = amm_account.load_mut()?;
+
+    let Some(batch_window_slots) = amm.batch_window_slots.get() else {
+      return err!(AmmError::NotBatchAuction);
+    };
+
+    amm.check_allowlisted(
+      amm_account.key(),
+      user.key(),
+      allowlist_entry.as_deref().map(|entry| &**entry),
+    )?;
+
+    require!(!amm.is_sunset(), AmmError::AmmSunset);
+
+    let current_slot = Clock::get()?.slot;
+
+    // the first order of a batch opens its window
+    if !batch_queue.has_pending_orders() {
+      batch_queue.window_start_slot = current_slot;
+    }
+
+    require_gt!(
+      batch_queue.window_start_slot + batch_window_slots,
+      current_slot,
+      AmmError::BatchWindowClosed
+    );
+    let (from, to) = match swap_type {
+      SwapType::Buy => (user_quote_account, vault_ata_quote),
+      SwapType::Sell => (user_base_account, vault_ata_base),
+    };
+
+    require_gte!(from.amount, input_amount, AmmError::InsufficientBalance);
+
+    let user_count = batch_queue.user_count();
+    batch_queue.commit(BatchOrder {
+      owner: user.key(),
+      swap_type,
+      input_amount,
+      min_output_amount,
+    })?;
+    amm.open_orders += batch_queue.user_count() - user_count;
+
+    token::transfer(
+      CpiContext::new(
+        token_program.to_account_info(),
+        Transfer {
+          from: from.to_account_info(),
+          to: to.to_account_info(),
+          authority: user.to_account_info(),
+        },
+      ),
+      input_amount,
+    )?;
+
+    Ok(())
+  }
+}
@@ -1,6 +1,10 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token::{self, CloseAccount, Mint, SyncNative, Token, TokenAccount, Transfer};
 
+use crate::error::AmmError;
 use crate::*;
 
 #[derive(Accounts)]
@@ -11,7 +15,7 @@ pub struct AddOrRemoveLiquidity<'info> {
         mut,
         has_one = lp_mint,
     )]
-  pub amm: Account<'info, Amm>,
+  pub amm: AccountLoader<'info, Amm>,
   #[account(mut)]
   pub lp_mint: Box<Account<'info, Mint>>,
   #[account(
@@ -20,29 +24,683 @@ pub struct AddOrRemoveLiquidity<'info> {
         token::authority = user,
     )]
   pub user_lp_account: Box<Account<'info, TokenAccount>>,
+  /// Left out on the pool's native mint side when wrapping SOL
   #[account(
         mut,
-        token::mint = amm.base_mint,
+        token::mint = amm.load()?.base_mint,
+        token::authority = user,
+    )]
+  pub user_base_account: Option<Box<Account<'info, TokenAccount>>>,
+  /// Left out on the pool's native mint side when wrapping SOL
+  #[account(
+        mut,
+        token::mint = amm.load()?.quote_mint,
+        token::authority = user,
+    )]
+  pub user_quote_account: Option<Box<Account<'info, TokenAccount>>>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.base_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_base: Account<'info, TokenAccount>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.quote_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_quote: Account<'info, TokenAccount>,
+  /// Required, with `lp_escrow`, to deposit into pools with `lp_lock_slots`.
+  /// Other pools, and withdrawals, don't touch it, so they don't pay its rent.
+  #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + LpPosition::INIT_SPACE,
+        seeds = [
+            LP_POSITION_SEED_PREFIX,
+            amm.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+  pub lp_position: Option<Box<Account<'info, LpPosition>>>,
+  /// Required to deposit into pools with `lp_lock_slots`, which mint new LP
+  /// tokens here until they unlock
+  #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = lp_mint,
+        associated_token::authority = lp_position,
+    )]
+  pub lp_escrow: Option<Box<Account<'info, TokenAccount>>>,
+  /// Required to add liquidity if the pool has a gate authority
+  pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+  /// Required with `native_sol_account`
+  #[account(address = native_mint::ID)]
+  pub native_mint: Option<Box<Account<'info, Mint>>>,
+  /// Pass to deposit from, and withdraw to, lamports on the pool's native
+  /// mint side, see `wrap_native_sol`
+  #[account(
+        init,
+        payer = user,
+        seeds = [NATIVE_SOL_ACCOUNT_SEED_PREFIX, user.key().as_ref()],
+        bump,
+        token::mint = native_mint,
+        token::authority = user,
+    )]
+  pub native_sol_account: Option<Box<Account<'info, TokenAccount>>>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub token_program: Program<'info, Token>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageOrder<'info> {
+  pub user: Signer<'info>,
+  #[account(mut)]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        mut,
+        has_one = amm,
+    )]
+  pub order_book: Box<Account<'info, OrderBook>>,
+  #[account(
+        mut,
+        token::mint = amm.load()?.base_mint,
+        token::authority = user,
+    )]
+  pub user_base_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = amm.load()?.quote_mint,
+        token::authority = user,
+    )]
+  pub user_quote_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.base_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_base: Account<'info, TokenAccount>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.quote_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_quote: Account<'info, TokenAccount>,
+  /// Required if the pool has a gate authority
+  pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+  pub token_program: Program<'info, Token>,
+}
+
+impl ManageOrder<'_> {
+  /// Sends an order's owner what they're owed out of the pool's vaults.
+  pub fn pay_out(&self, base_amount: u64, quote_amount: u64) -> Result<()> {
+    pay_out_from_vaults(
+      &self.amm,
+      &self.token_program,
+      [
+        (base_amount, &self.vault_ata_base, &self.user_base_account),
+        (quote_amount, &self.vault_ata_quote, &self.user_quote_account),
+      ],
+    )
+  }
+}
+
+#[derive(Accounts)]
+pub struct ManageBatchOrder<'info> {
+  pub user: Signer<'info>,
+  #[account(mut)]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        mut,
+        has_one = amm,
+    )]
+  pub batch_queue: Box<Account<'info, BatchQueue>>,
+  #[account(
+        mut,
+        token::mint = amm.load()?.base_mint,
         token::authority = user,
     )]
   pub user_base_account: Box<Account<'info, TokenAccount>>,
   #[account(
         mut,
-        token::mint = amm.quote_mint,
+        token::mint = amm.load()?.quote_mint,
         token::authority = user,
     )]
   pub user_quote_account: Box<Account<'info, TokenAccount>>,
   #[account(
         mut,
-        associated_token::mint = amm.base_mint,
+        associated_token::mint = amm.load()?.base_mint,
         associated_token::authority = amm,
     )]
   pub vault_ata_base: Account<'info, TokenAccount>,
   #[account(
         mut,
-        associated_token::mint = amm.quote_mint,
+        associated_token::mint = amm.load()?.quote_mint,
         associated_token::authority = amm,
     )]
   pub vault_ata_quote: Account<'info, TokenAccount>,
+  /// Required if the pool has a gate authority
+  pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+  pub token_program: Program<'info, Token>,
+}
+
+/// Transfers each non-zero amount out of a pool's vault, signed by the pool.
+pub fn pay_out_from_vaults<'info>(
+  amm: &AccountLoader<'info, Amm>,
+  token_program: &Program<'info, Token>,
+  transfers: [(u64, &Account<'info, TokenAccount>, &Account<'info, TokenAccount>); 2],
+) -> Result<()> {
+  let amm_signer = amm.load()?.signer();
+  let seeds = generate_amm_seeds!(amm_signer);
+
+  for (amount, from, to) in transfers {
+    if amount == 0 {
+      continue;
+    }
+
+    token::transfer(
+      CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Transfer {
+          from: from.to_account_info(),
+          to: to.to_account_info(),
+          authority: amm.to_account_info(),
+        },
+        &[seeds],
+      ),
+      amount,
+    )?;
+  }
+
+  Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ManageStake<'info> {
+  #[account(mut)]
+  pub user: Signer<'info>,
+  #[account(mut)]
+  pub reward_pool: Box<Account<'info, RewardPool>>,
+  #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + StakeAccount::INIT_SPACE,
+        seeds = [
+            STAKE_ACCOUNT_SEED_PREFIX,
+            reward_pool.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+  pub stake_account: Box<Account<'info, StakeAccount>>,
+  #[account(
+        mut,
+        token::mint = reward_pool.lp_mint,
+        token::authority = user,
+    )]
+  pub user_lp_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        associated_token::mint = reward_pool.lp_mint,
+        associated_token::authority = reward_pool,
+    )]
+  pub stake_vault: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = reward_pool.reward_mint,
+        token::authority = user,
+    )]
+  pub user_reward_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        associated_token::mint = reward_pool.reward_mint,
+        associated_token::authority = reward_pool,
+    )]
+  pub reward_vault: Box<Account<'info, TokenAccount>>,
+  pub token_program: Program<'info, Token>,
+  pub system_program: Program<'info, System>,
+}
+
+impl<'info> ManageStake<'info> {
+  /// Transfers out of one of the reward pool's vaults, signed by the pool.
+  pub fn pay_out(&self, from: &Account<'info, TokenAccount>, to: &Account<'info, TokenAccount>, amount: u64) -> Result<()> {
+    if amount == 0 {
+      return Ok(());
+    }
+
+    let seeds = generate_reward_pool_seeds!(self.reward_pool);
+
+    token::transfer(
+      CpiContext::new_with_signer(
+        self.token_program.to_account_info(),
+        Transfer {
+          from: from.to_account_info(),
+          to: to.to_account_info(),
+          authority: self.reward_pool.to_account_info(),
+        },
+        &[seeds],
+      ),
+      amount,
+    )
+  }
+}
+
+#[derive(Accounts)]
+pub struct ManageLpLocker<'info> {
+  pub owner: Signer<'info>,
+  #[account(
+        mut,
+        has_one = lp_mint,
+    )]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(mut)]
+  pub lp_mint: Box<Account<'info, Mint>>,
+  #[account(
+        mut,
+        has_one = owner,
+        has_one = amm,
+    )]
+  pub lp_locker: Box<Account<'info, LpLocker>>,
+  #[account(
+        mut,
+        associated_token::mint = lp_mint,
+        associated_token::authority = lp_locker,
+    )]
+  pub locker_vault: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = lp_mint,
+        token::authority = owner,
+    )]
+  pub owner_lp_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = amm.load()?.base_mint,
+        token::authority = owner,
+    )]
+  pub owner_base_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = amm.load()?.quote_mint,
+        token::authority = owner,
+    )]
+  pub owner_quote_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.base_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_base: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.quote_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_quote: Box<Account<'info, TokenAccount>>,
+  /// Only for lockers staked in a reward pool
+  #[account(mut)]
+  pub reward_pool: Option<Box<Account<'info, RewardPool>>>,
+  /// Only for lockers staked in a reward pool
+  #[account(mut)]
+  pub stake_account: Option<Box<Account<'info, StakeAccount>>>,
   pub token_program: Program<'info, Token>,
 }
+
+impl ManageLpLocker<'_> {
+  /// Takes LP tokens that are leaving the locker out of its reward pool
+  /// stake, if it has one.
+  pub fn unstake_from_reward_pool(&mut self, amount: u64) -> Result<()> {
+    match (self.lp_locker.reward_pool, &mut self.reward_pool, &mut self.stake_account) {
+      (None, None, None) => Ok(()),
+      (Some(reward_pool_key), Some(reward_pool), Some(stake_account)) => {
+        require_keys_eq!(reward_pool.key(), reward_pool_key, AmmError::InvalidLockerRewardAccounts);
+        require_keys_eq!(
+          stake_account.reward_pool,
+          reward_pool_key,
+          AmmError::InvalidLockerRewardAccounts
+        );
+        require_keys_eq!(
+          stake_account.owner,
+          self.lp_locker.key(),
+          AmmError::InvalidLockerRewardAccounts
+        );
+
+        if amount == 0 {
+          return Ok(());
+        }
+
+        reward_pool.unstake(stake_account, amount, Clock::get()?.slot)
+      }
+      _ => err!(AmmError::InvalidLockerRewardAccounts),
+    }
+  }
+}
+
+impl<'info> AddOrRemoveLiquidity<'info> {
+  /// The user's base and quote token accounts, with `native_sol_account` in
+  /// place of one of them if the user is wrapping SOL.
+  pub fn user_token_accounts(&mut self) -> Result<(&mut Account<'info, TokenAccount>, &mut Account<'info, TokenAccount>)> {
+    user_token_accounts(
+      &*self.amm.load()?,
+      &mut self.user_base_account,
+      &mut self.user_quote_account,
+      &mut self.native_sol_account,
+    )
+  }
+
+  /// If the user is wrapping SOL, tops `native_sol_account` up with
+  /// `base_amount` or `quote_amount`, whichever is on the native side.
+  pub fn wrap_native_sol(&mut self, base_amount: u64, quote_amount: u64) -> Result<()> {
+    let Some(native_sol_account) = &mut self.native_sol_account else {
+      return Ok(());
+    };
+
+    let (is_base_native, _) = native_sol_sides(&*self.amm.load()?)?;
+
+    let amount = if is_base_native { base_amount } else { quote_amount };
+
+    wrap_native_sol(
+      &self.user,
+      native_sol_account,
+      amount,
+      &self.system_program,
+      &self.token_program,
+    )
+  }
+
+  /// If the user is wrapping SOL, closes `native_sol_account`, unwrapping
+  /// whatever it holds back to them.
+  pub fn unwrap_native_sol(&self) -> Result<()> {
+    match &self.native_sol_account {
+      Some(native_sol_account) => unwrap_native_sol(&self.user, native_sol_account, &self.token_program),
+      None => Ok(()),
+    }
+  }
+}
+
+/// Which of a pool's sides, base and quote, is the native mint. Errors if
+/// neither is.
+pub fn native_sol_sides(amm: &Amm) -> Result<(bool, bool)> {
+  let sides = (amm.base_mint == native_mint::ID, amm.quote_mint == native_mint::ID);
+
+  require!(sides.0 || sides.1, AmmError::NotNativeSolPool);
+
+  Ok(sides)
+}
+
+/// Picks the user's base and quote token accounts. If the user is wrapping
+/// SOL, `native_sol_account` stands in for their account on the pool's
+/// native mint side, which they must leave out, so that their own wSOL is
+/// never touched.
+pub fn user_token_accounts<'a, 'info>(
+  amm: &Amm,
+  user_base_account: &'a mut Option<Box<Account<'info, TokenAccount>>>,
+  user_quote_account: &'a mut Option<Box<Account<'info, TokenAccount>>>,
+  native_sol_account: &'a mut Option<Box<Account<'info, TokenAccount>>>,
+) -> Result<(&'a mut Account<'info, TokenAccount>, &'a mut Account<'info, TokenAccount>)> {
+  let accounts = match native_sol_account {
+    Some(native_sol_account) => {
+      let (is_base_native, _) = native_sol_sides(amm)?;
+
+      if is_base_native {
+        require!(user_base_account.is_none(), AmmError::MissingTokenAccount);
+
+        (Some(&mut **native_sol_account), user_quote_account.as_deref_mut())
+      } else {
+        require!(user_quote_account.is_none(), AmmError::MissingTokenAccount);
+
+        (user_base_account.as_deref_mut(), Some(&mut **native_sol_account))
+      }
+    }
+    None => (user_base_account.as_deref_mut(), user_quote_account.as_deref_mut()),
+  };
+
+  match accounts {
+    (Some(base_account), Some(quote_account)) => Ok((base_account, quote_account)),
+    _ => err!(AmmError::MissingTokenAccount),
+  }
+}
+
+/// Tops `native_sol_account` up with `amount` of `user`'s lamports. This is
+/// what lets native SOL pools take SOL directly: the pool itself only ever
+/// sees wSOL, so its accounting doesn't change.
+pub fn wrap_native_sol<'info>(
+  user: &Signer<'info>,
+  native_sol_account: &mut Account<'info, TokenAccount>,
+  amount: u64,
+  system_program: &Program<'info, System>,
+  token_program: &Program<'info, Token>,
+) -> Result<()> {
+  if amount == 0 {
+    return Ok(());
+  }
+
+  system_program::transfer(
+    CpiContext::new(
+      system_program.to_account_info(),
+      system_program::Transfer {
+        from: user.to_account_info(),
+        to: native_sol_account.to_account_info(),
+      },
+    ),
+    amount,
+  )?;
+
+  token::sync_native(CpiContext::new(
+    token_program.to_account_info(),
+    SyncNative {
+      account: native_sol_account.to_account_info(),
+    },
+  ))?;
+
+  native_sol_account.reload()
+}
+
+/// Closes `native_sol_account`, which sends all of its SOL, rent included,
+/// back to `user`.
+pub fn unwrap_native_sol<'info>(
+  user: &Signer<'info>,
+  native_sol_account: &Account<'info, TokenAccount>,
+  token_program: &Program<'info, Token>,
+) -> Result<()> {
+  token::close_account(CpiContext::new(
+    token_program.to_account_info(),
+    CloseAccount {
+      account: native_sol_account.to_account_info(),
+      destination: user.to_account_info(),
+      authority: user.to_account_info(),
+    },
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use anchor_lang::solana_program::instruction::Instruction;
+  use anchor_lang::solana_program::program_pack::Pack;
+  use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+  use anchor_lang::solana_program::program_utils::limited_deserialize;
+  use anchor_lang::solana_program::system_instruction::SystemInstruction;
+  use anchor_lang::solana_program::{bpf_loader, entrypoint::ProgramResult, system_program as system};
+  use anchor_spl::token::spl_token::{self, processor::Processor};
+
+  /// Runs the system program's transfers and the token program natively, so
+  /// that the wrapping CPIs can run outside of a validator.
+  struct CpiStubs;
+
+  impl SyscallStubs for CpiStubs {
+    fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], _: &[&[&[u8]]]) -> ProgramResult {
+      let accounts: Vec<AccountInfo> = instruction
+        .accounts
+        .iter()
+        .map(|meta| account_infos.iter().find(|info| info.key == &meta.pubkey).unwrap().clone())
+        .collect();
+
+      if instruction.program_id == system::ID {
+        let SystemInstruction::Transfer { lamports } = limited_deserialize(&instruction.data, 1024).unwrap() else {
+          unimplemented!()
+        };
+
+        **accounts[0].try_borrow_mut_lamports()? -= lamports;
+        **accounts[1].try_borrow_mut_lamports()? += lamports;
+
+        Ok(())
+      } else {
+        Processor::process(&instruction.program_id, &accounts, &instruction.data)
+      }
+    }
+  }
+
+  fn account_info(
+    key: Pubkey,
+    is_signer: bool,
+    lamports: u64,
+    data: Vec<u8>,
+    owner: Pubkey,
+    executable: bool,
+  ) -> &'static AccountInfo<'static> {
+    Box::leak(Box::new(AccountInfo::new(
+      Box::leak(Box::new(key)),
+      is_signer,
+      true,
+      Box::leak(Box::new(lamports)),
+      Box::leak(data.into_boxed_slice()),
+      Box::leak(Box::new(owner)),
+      executable,
+      0,
+    )))
+  }
+
+  fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Box<Account<'static, TokenAccount>> {
+    let rent_exempt_reserve = Rent::default().minimum_balance(spl_token::state::Account::LEN);
+    let is_native = mint == native_mint::ID;
+
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+      mint,
+      owner,
+      amount,
+      state: spl_token::state::AccountState::Initialized,
+      is_native: is_native.then_some(rent_exempt_reserve).into(),
+      ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+
+    let lamports = if is_native {
+      rent_exempt_reserve + amount
+    } else {
+      rent_exempt_reserve
+    };
+
+    let info = account_info(Pubkey::new_unique(), false, lamports, data, spl_token::ID, false);
+
+    Box::new(Account::try_from(info).unwrap())
+  }
+
+  fn sol_pool(base_mint: Pubkey) -> Amm {
+    Amm {
+      base_mint,
+      quote_mint: native_mint::ID,
+      ..Amm::default()
+    }
+  }
+
+  #[test]
+  fn native_sol_account_replaces_the_native_side() {
+    let (user, base_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let amm = sol_pool(base_mint);
+
+    let mut user_base_account = Some(token_account(base_mint, user, 0));
+    let native_sol_account = Some(token_account(native_mint::ID, user, 0));
+    let native_sol_key = native_sol_account.as_ref().unwrap().key();
+
+    let (mut user_quote_account, mut wrapped_account) = (None, native_sol_account.clone());
+    let (base_account, quote_account) =
+      user_token_accounts(&amm, &mut user_base_account, &mut user_quote_account, &mut wrapped_account).unwrap();
+
+    assert_eq!(base_account.mint, base_mint);
+    assert_eq!(quote_account.key(), native_sol_key);
+
+    // the user's own wSOL account can't be passed alongside it
+    let user_wsol_account = Some(token_account(native_mint::ID, user, 0));
+
+    assert_eq!(
+      user_token_accounts(
+        &amm,
+        &mut user_base_account,
+        &mut user_wsol_account.clone(),
+        &mut native_sol_account.clone()
+      )
+      .unwrap_err(),
+      AmmError::MissingTokenAccount.into()
+    );
+
+    // the other side still needs the user's account
+    assert_eq!(
+      user_token_accounts(&amm, &mut None, &mut None, &mut native_sol_account.clone()).unwrap_err(),
+      AmmError::MissingTokenAccount.into()
+    );
+
+    // and without wrapping, both do
+    assert!(user_token_accounts(&amm, &mut user_base_account, &mut user_wsol_account.clone(), &mut None).is_ok());
+    assert_eq!(
+      user_token_accounts(&amm, &mut user_base_account, &mut None, &mut None).unwrap_err(),
+      AmmError::MissingTokenAccount.into()
+    );
+
+    // and pools without a native side can't wrap
+    let amm = Amm {
+      quote_mint: Pubkey::new_unique(),
+      ..amm
+    };
+
+    assert_eq!(
+      user_token_accounts(&amm, &mut user_base_account, &mut None, &mut native_sol_account.clone()).unwrap_err(),
+      AmmError::NotNativeSolPool.into()
+    );
+  }
+
+  #[test]
+  fn wrapping_and_unwrapping_round_trips_the_users_lamports() {
+    set_syscall_stubs(Box::new(CpiStubs));
+
+    let user_lamports = 10_000_000_000;
+    let user_info = account_info(Pubkey::new_unique(), true, user_lamports, vec![], system::ID, false);
+    let user = Signer::try_from(user_info).unwrap();
+
+    let system_program = Program::<System>::try_from(account_info(system::ID, false, 1, vec![], bpf_loader::ID, true)).unwrap();
+    let token_program = Program::<Token>::try_from(account_info(spl_token::ID, false, 1, vec![], bpf_loader::ID, true)).unwrap();
+
+    let mut native_sol_account = token_account(native_mint::ID, user.key(), 0);
+    let rent_exempt_reserve = native_sol_account.to_account_info().lamports();
+
+    wrap_native_sol(&user, &mut native_sol_account, 1_000_000, &system_program, &token_program).unwrap();
+
+    assert_eq!(native_sol_account.amount, 1_000_000);
+    assert_eq!(user_info.lamports(), user_lamports - 1_000_000);
+
+    // what's left after the pool takes its input, or pays out its output,
+    // all goes back to the user, rent included
+    let native_sol_info = native_sol_account.to_account_info();
+    **native_sol_info.try_borrow_mut_lamports().unwrap() += 250_000;
+
+    token::sync_native(CpiContext::new(
+      token_program.to_account_info(),
+      SyncNative {
+        account: native_sol_account.to_account_info(),
+      },
+    ))
+    .unwrap();
+
+    unwrap_native_sol(&user, &native_sol_account, &token_program).unwrap();
+
+    assert_eq!(native_sol_info.lamports(), 0);
+    assert_eq!(native_sol_info.owner, &system::ID);
+    assert_eq!(
+      user_info.lamports(),
+      user_lamports - 1_000_000 + 1_000_000 + 250_000 + rent_exempt_reserve
+    );
+  }
+}
@@ -5,14 +5,14 @@ use crate::state::*;
 #[derive(Accounts)]
 pub struct CrankThatTwap<'info> {
   #[account(mut)]
-  pub amm: Account<'info, Amm>,
+  pub amm: AccountLoader<'info, Amm>,
 }
 
 impl CrankThatTwap<'_> {
   pub fn handle(ctx: Context<Self>) -> Result<()> {
     let CrankThatTwap { amm } = ctx.accounts;
 
-    amm.update_twap(Clock::get()?.slot);
+    amm.load_mut()?.update_twap(Clock::get()?.slot);
 
     Ok(())
   }
@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::clock::Slot;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::*;
 
@@ -9,6 +10,29 @@ use crate::state::*;
 pub struct CreateAmmArgs {
   pub twap_initial_observation: u128,
   pub twap_max_observation_change_per_update: u128,
+  pub twap_max_observation_change_mode: ObservationChangeMode,
+  /// The slot the TWAP starts aggregating from, defaults to the current slot
+  pub twap_start_slot: Option<u64>,
+  /// The slot after which the TWAP stops aggregating, if any
+  pub twap_end_slot: Option<u64>,
+  /// The furthest, in basis points, that a swap can move the spot price
+  /// away from the TWAP's last observation, if any
+  pub circuit_breaker_bps: Option<u16>,
+  /// The smallest `quote_amount` the first deposit can have, defaults to
+  /// one whole quote token
+  pub min_initial_quote_amount: Option<u64>,
+  /// The slice of the swap fee, in basis points of the fee, that goes to
+  /// referrers registered with `add_referrer`
+  pub referral_fee_bps: u16,
+  /// If set, puts the pool in batch-auction mode, with swaps collected for
+  /// this many slots and then cleared at one price
+  pub batch_window_slots: Option<u64>,
+  /// If set, new LP tokens are escrowed until this many slots after their
+  /// owner's last deposit
+  pub lp_lock_slots: Option<u64>,
+  /// If set, only users allowlisted by this authority can trade or provide
+  /// liquidity
+  pub gate_authority: Option<Pubkey>,
 }
 
 #[derive(Accounts)]
@@ -19,7 +43,7 @@ pub struct CreateAmm<'info> {
   #[account(
         init,
         payer = user,
-        space = 8 + std::mem::size_of::<Amm>(),
+        space = Amm::SPACE,
         seeds = [
             AMM_SEED_PREFIX,
             base_mint.key().as_ref(),
@@ -27,7 +51,7 @@ pub struct CreateAmm<'info> {
         ],
         bump
     )]
-  pub amm: Box<Account<'info, Amm>>,
+  pub amm: AccountLoader<'info, Amm>,
   #[account(
         init,
         payer = user,
@@ -35,7 +59,7 @@ pub struct CreateAmm<'info> {
         bump,
         mint::authority = amm,
         mint::freeze_authority = amm,
-        mint::decimals = 9,
+        mint::decimals = quote_mint.decimals,
     )]
   pub lp_mint: Box<Account<'info, Mint>>,
   pub base_mint: Box<Account<'info, Mint>>,
@@ -64,7 +88,7 @@ impl CreateAmm<'_> {
 
   pub fn handle(ctx: Context<Self>, args: CreateAmmArgs) -> Result<()> {
     let CreateAmm {
-      user: _,
+      user,
       amm,
       lp_mint,
       base_mint,
@@ -76,19 +100,85 @@ impl CreateAmm<'_> {
       system_program: _,
     } = ctx.accounts;
 
-    let current_slot = Clock::get()?.slot;
+    *amm.load_init()? = args.into_amm(
+      Clock::get()?.slot,
+      ctx.bumps.amm,
+      user.key(),
+      lp_mint.key(),
+      base_mint,
+      quote_mint,
+    )?;
+
+    Ok(())
+  }
+}
 
+impl CreateAmmArgs {
+  /// Validates the args and builds the empty `Amm` that they describe.
+  pub fn into_amm(
+    self,
+    current_slot: Slot,
+    bump: u8,
+    admin: Pubkey,
+    lp_mint: Pubkey,
+    base_mint: &Account<Mint>,
+    quote_mint: &Account<Mint>,
+  ) -> Result<Amm> {
     let CreateAmmArgs {
       twap_initial_observation,
       twap_max_observation_change_per_update,
-    } = args;
+      twap_max_observation_change_mode,
+      twap_start_slot,
+      twap_end_slot,
+      circuit_breaker_bps,
+      min_initial_quote_amount,
+      referral_fee_bps,
+      batch_window_slots,
+      lp_lock_slots,
+      gate_authority,
+    } = self;
+
+    require!(circuit_breaker_bps != Some(0), AmmError::InvalidCircuitBreaker);
+    require!(batch_window_slots != Some(0), AmmError::InvalidBatchWindow);
+    require!(lp_lock_slots != Some(0), AmmError::InvalidLpLock);
+    require_gte!(MAX_BPS, referral_fee_bps, AmmError::InvalidReferralFee);
+
+    let min_initial_quote_amount = match min_initial_quote_amount {
+      Some(min_initial_quote_amount) => {
+        require_neq!(min_initial_quote_amount, 0, AmmError::InsufficientQuoteAmount);
+
+        min_initial_quote_amount
+      }
+      None => Amm::default_min_initial_quote_amount(quote_mint.decimals),
+    };
+
+    let twap_start_slot = twap_start_slot.map_or(current_slot, |start_slot| start_slot.max(current_slot));
+
+    if let Some(twap_end_slot) = twap_end_slot {
+      // leave room for at least one observation
+      require_gte!(
+        twap_end_slot,
+        twap_start_slot + ONE_MINUTE_IN_SLOTS,
+        AmmError::InvalidTwapWindow
+      );
+    }
+
+    if twap_max_observation_change_mode == ObservationChangeMode::Relative {
+      require_neq!(twap_initial_observation, 0, AmmError::InvalidRelativeObservationChange);
+      require_neq!(
+        twap_max_observation_change_per_update,
+        0,
+        AmmError::InvalidRelativeObservationChange
+      );
+    }
 
-    amm.set_inner(Amm {
-      bump: ctx.bumps.amm,
+    Ok(Amm {
+      version: AMM_VERSION,
+      bump,
 
       created_at_slot: current_slot,
 
-      lp_mint: lp_mint.key(),
+      lp_mint,
       base_mint: base_mint.key(),
       quote_mint: quote_mint.key(),
 
@@ -98,9 +188,36 @@ impl CreateAmm<'_> {
       base_amount: 0,
       quote_amount: 0,
 
-      oracle: TwapOracle::new(current_slot, twap_initial_observation, twap_max_observation_change_per_update),
-    });
+      oracle: TwapOracle::new(
+        twap_start_slot,
+        twap_initial_observation,
+        twap_max_observation_change_per_update,
+        twap_max_observation_change_mode,
+        twap_end_slot,
+      ),
 
-    Ok(())
+      admin,
+      circuit_breaker_bps: circuit_breaker_bps.into(),
+
+      min_initial_quote_amount,
+
+      sunset_slot: None.into(),
+
+      referral_fee_bps,
+
+      open_orders: 0,
+
+      batch_window_slots: batch_window_slots.into(),
+      last_clearing_price: None.into(),
+
+      lp_lock_slots: lp_lock_slots.into(),
+      locked_lp_tokens: 0,
+
+      gate_authority: gate_authority.into(),
+
+      stats: AmmStats::default(),
+
+      ..Amm::default()
+    })
   }
 }
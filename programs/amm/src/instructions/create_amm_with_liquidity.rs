@@ -0,0 +1,245 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, *};
+
+use crate::error::AmmError;
+use crate::{generate_amm_seeds, state::*, CreateAmmArgs};
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+/// `CreateAmmArgs` without `twap_initial_observation`, which is the price
+/// implied by the deposit, and with the deposit itself.
+pub struct CreateAmmWithLiquidityArgs {
+  pub twap_max_observation_change_per_update: u128,
+  pub twap_max_observation_change_mode: ObservationChangeMode,
+  pub twap_start_slot: Option<u64>,
+  pub twap_end_slot: Option<u64>,
+  pub circuit_breaker_bps: Option<u16>,
+  pub min_initial_quote_amount: Option<u64>,
+  pub referral_fee_bps: u16,
+  pub batch_window_slots: Option<u64>,
+  pub lp_lock_slots: Option<u64>,
+  pub gate_authority: Option<Pubkey>,
+  /// How much base token you will deposit to the pool
+  pub base_amount: u64,
+  /// How much quote token you will deposit to the pool
+  pub quote_amount: u64,
+}
+
+#[derive(Accounts)]
+pub struct CreateAmmWithLiquidity<'info> {
+  #[account(mut)]
+  pub user: Signer<'info>,
+  #[account(
+        init,
+        payer = user,
+        space = Amm::SPACE,
+        seeds = [
+            AMM_SEED_PREFIX,
+            base_mint.key().as_ref(),
+            quote_mint.key().as_ref()
+        ],
+        bump
+    )]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        init,
+        payer = user,
+        seeds = [AMM_LP_MINT_SEED_PREFIX, amm.key().as_ref()],
+        bump,
+        mint::authority = amm,
+        mint::freeze_authority = amm,
+        mint::decimals = quote_mint.decimals,
+    )]
+  pub lp_mint: Box<Account<'info, Mint>>,
+  pub base_mint: Box<Account<'info, Mint>>,
+  pub quote_mint: Box<Account<'info, Mint>>,
+  #[account(
+        init,
+        payer = user,
+        associated_token::mint = lp_mint,
+        associated_token::authority = user,
+    )]
+  pub user_lp_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = base_mint,
+        token::authority = user,
+    )]
+  pub user_base_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = quote_mint,
+        token::authority = user,
+    )]
+  pub user_quote_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        associated_token::authority = amm,
+        associated_token::mint = base_mint
+    )]
+  pub vault_ata_base: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        associated_token::authority = amm,
+        associated_token::mint = quote_mint
+    )]
+  pub vault_ata_quote: Box<Account<'info, TokenAccount>>,
+  /// Required, with `lp_escrow`, if the pool locks liquidity
+  #[account(
+        init,
+        payer = user,
+        space = 8 + LpPosition::INIT_SPACE,
+        seeds = [
+            LP_POSITION_SEED_PREFIX,
+            amm.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+  pub lp_position: Option<Box<Account<'info, LpPosition>>>,
+  /// Required if the pool locks liquidity, see `LpPosition`
+  #[account(
+        init,
+        payer = user,
+        associated_token::mint = lp_mint,
+        associated_token::authority = lp_position,
+    )]
+  pub lp_escrow: Option<Box<Account<'info, TokenAccount>>>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub token_program: Program<'info, Token>,
+  pub system_program: Program<'info, System>,
+}
+
+impl CreateAmmWithLiquidity<'_> {
+  pub fn validate(&self) -> Result<()> {
+    require_neq!(self.base_mint.key(), self.quote_mint.key(), AmmError::SameTokenMints);
+
+    Ok(())
+  }
+
+  pub fn handle(ctx: Context<Self>, args: CreateAmmWithLiquidityArgs) -> Result<()> {
+    let CreateAmmWithLiquidity {
+      user,
+      amm: amm_account,
+      lp_mint,
+      base_mint,
+      quote_mint,
+      user_lp_account,
+      user_base_account,
+      user_quote_account,
+      vault_ata_base,
+      vault_ata_quote,
+      lp_position,
+      lp_escrow,
+      associated_token_program: _,
+      token_program,
+      system_program: _,
+    } = ctx.accounts;
+
+    let CreateAmmWithLiquidityArgs {
+      twap_max_observation_change_per_update,
+      twap_max_observation_change_mode,
+      twap_start_slot,
+      twap_end_slot,
+      circuit_breaker_bps,
+      min_initial_quote_amount,
+      referral_fee_bps,
+      batch_window_slots,
+      lp_lock_slots,
+      gate_authority,
+      base_amount,
+      quote_amount,
+    } = args;
+
+    require_gte!(user_base_account.amount, base_amount, AmmError::InsufficientBalance);
+    require_gte!(user_quote_account.amount, quote_amount, AmmError::InsufficientBalance);
+
+    require!(base_amount > 0, AmmError::ZeroLiquidityToAdd);
+    require!(quote_amount > 0, AmmError::ZeroLiquidityToAdd);
+
+    // nobody can slip a deposit in between creation and seeding, so the
+    // deposited ratio is the pool's real starting price
+    let create_amm_args = CreateAmmArgs {
+      twap_initial_observation: (quote_amount as u128 * PRICE_SCALE) / base_amount as u128,
+      twap_max_observation_change_per_update,
+      twap_max_observation_change_mode,
+      twap_start_slot,
+      twap_end_slot,
+      circuit_breaker_bps,
+      min_initial_quote_amount,
+      referral_fee_bps,
+      batch_window_slots,
+      lp_lock_slots,
+      gate_authority,
+    };
+
+    let current_slot = Clock::get()?.slot;
+
+    let mut amm = amm_account.load_init()?;
+
+    *amm = create_amm_args.into_amm(current_slot, ctx.bumps.amm, user.key(), lp_mint.key(), base_mint, quote_mint)?;
+
+    require_gte!(quote_amount, amm.min_initial_quote_amount, AmmError::InsufficientQuoteAmount);
+
+    amm.base_amount = base_amount;
+    amm.quote_amount = quote_amount;
+
+    let lp_destination = if amm.lp_lock_slots.get().is_some() {
+      let (Some(lp_position), Some(lp_escrow)) = (lp_position, lp_escrow) else {
+        return err!(AmmError::MissingLpEscrow);
+      };
+
+      lp_position.set_inner(LpPosition {
+        amm: amm_account.key(),
+        owner: user.key(),
+        deposit_slot: current_slot,
+        bump: ctx.bumps.lp_position,
+      });
+
+      lp_escrow.to_account_info()
+    } else {
+      user_lp_account.to_account_info()
+    };
+
+    let amm_signer = amm.signer();
+    drop(amm);
+
+    // same as the first deposit in `add_liquidity`
+    let initial_lp_tokens = quote_amount;
+
+    let seeds = generate_amm_seeds!(amm_signer);
+    let signer = &[&seeds[..]];
+
+    token::mint_to(
+      CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        MintTo {
+          mint: lp_mint.to_account_info(),
+          to: lp_destination,
+          authority: amm_account.to_account_info(),
+        },
+        signer,
+      ),
+      initial_lp_tokens,
+    )?;
+
+    for (amount, from, to) in [
+      (base_amount, user_base_account, vault_ata_base),
+      (quote_amount, user_quote_account, vault_ata_quote),
+    ] {
+      token::transfer(
+        CpiContext::new(
+          token_program.to_account_info(),
+          Transfer {
+            from: from.to_account_info(),
+            to: to.to_account_info(),
+            authority: user.to_account_info(),
+          },
+        ),
+        amount,
+      )?;
+    }
+
+    Ok(())
+  }
+}
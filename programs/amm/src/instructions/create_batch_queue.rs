@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateBatchQueueArgs {
+  /// The smallest buy the queue takes, in quote units
+  pub min_quote_amount: u64,
+  /// The smallest sell the queue takes, in base units
+  pub min_base_amount: u64,
+}
+
+#[derive(Accounts)]
+pub struct CreateBatchQueue<'info> {
+  #[account(mut)]
+  pub admin: Signer<'info>,
+  #[account(has_one = admin)]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        init,
+        payer = admin,
+        space = 8 + BatchQueue::INIT_SPACE,
+        seeds = [AMM_BATCH_QUEUE_SEED_PREFIX, amm.key().as_ref()],
+        bump
+    )]
+  pub batch_queue: Box<Account<'info, BatchQueue>>,
+  pub system_program: Program<'info, System>,
+}
+
+impl CreateBatchQueue<'_> {
+  pub fn handle(ctx: Context<Self>, args: CreateBatchQueueArgs) -> Result<()> {
+    let CreateBatchQueue {
+      admin: _,
+      amm,
+      batch_queue,
+      system_program: _,
+    } = ctx.accounts;
+
+    {
+      let amm = amm.load()?;
+
+      require!(amm.is_batch_auction(), AmmError::NotBatchAuction);
+      require!(!amm.is_sunset(), AmmError::AmmSunset);
+    }
+
+    batch_queue.set_inner(BatchQueue {
+      amm: amm.key(),
+      bump: ctx.bumps.batch_queue,
+      window_start_slot: 0,
+      min_quote_amount: args.min_quote_amount,
+      min_base_amount: args.min_base_amount,
+      orders: vec![],
+      balances: vec![],
+    });
+
+    Ok(())
+  }
+}
@@ -0,0 +1,122 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, *};
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateLpLockerArgs {
+  pub locker_id: u64,
+  pub lp_amount: u64,
+  pub vesting: LockerVesting,
+  /// When linear vesting starts, defaults to now
+  pub start_timestamp: Option<i64>,
+  /// When everything has unlocked
+  pub end_timestamp: i64,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateLpLockerArgs)]
+pub struct CreateLpLocker<'info> {
+  #[account(mut)]
+  pub owner: Signer<'info>,
+  #[account(
+        mut,
+        has_one = lp_mint,
+    )]
+  pub amm: AccountLoader<'info, Amm>,
+  pub lp_mint: Box<Account<'info, Mint>>,
+  #[account(
+        init,
+        payer = owner,
+        space = 8 + std::mem::size_of::<LpLocker>(),
+        seeds = [
+            LP_LOCKER_SEED_PREFIX,
+            amm.key().as_ref(),
+            owner.key().as_ref(),
+            &args.locker_id.to_le_bytes()
+        ],
+        bump
+    )]
+  pub lp_locker: Box<Account<'info, LpLocker>>,
+  #[account(
+        init,
+        payer = owner,
+        associated_token::mint = lp_mint,
+        associated_token::authority = lp_locker,
+    )]
+  pub locker_vault: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = lp_mint,
+        token::authority = owner,
+    )]
+  pub owner_lp_account: Box<Account<'info, TokenAccount>>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub token_program: Program<'info, Token>,
+  pub system_program: Program<'info, System>,
+}
+
+impl CreateLpLocker<'_> {
+  pub fn handle(ctx: Context<Self>, args: CreateLpLockerArgs) -> Result<()> {
+    let CreateLpLocker {
+      owner,
+      amm: amm_account,
+      lp_mint,
+      lp_locker,
+      locker_vault,
+      owner_lp_account,
+      associated_token_program: _,
+      token_program,
+      system_program: _,
+    } = ctx.accounts;
+
+    let CreateLpLockerArgs {
+      locker_id,
+      lp_amount,
+      vesting,
+      start_timestamp,
+      end_timestamp,
+    } = args;
+
+    let now = Clock::get()?.unix_timestamp;
+    let start_timestamp = start_timestamp.unwrap_or(now);
+
+    require!(lp_amount > 0, AmmError::InvalidLockerSchedule);
+    require_gt!(end_timestamp, start_timestamp, AmmError::InvalidLockerSchedule);
+    require_gt!(end_timestamp, now, AmmError::InvalidLockerSchedule);
+    require_gte!(owner_lp_account.amount, lp_amount, AmmError::InsufficientBalance);
+
+    let mut amm = amm_account.load_mut()?;
+
+    lp_locker.set_inner(LpLocker {
+      bump: ctx.bumps.lp_locker,
+      amm: amm_account.key(),
+      lp_mint: lp_mint.key(),
+      owner: owner.key(),
+      locker_id,
+      vesting,
+      start_timestamp,
+      end_timestamp,
+      total_amount: lp_amount,
+      withdrawn_amount: 0,
+      liquidity_per_lp_token_claimed: amm.liquidity_per_lp_token(lp_mint.supply).unwrap_or_default(),
+      reward_pool: None,
+    });
+
+    amm.locked_lp_tokens += lp_amount;
+
+    token::transfer(
+      CpiContext::new(
+        token_program.to_account_info(),
+        Transfer {
+          from: owner_lp_account.to_account_info(),
+          to: locker_vault.to_account_info(),
+          authority: owner.to_account_info(),
+        },
+      ),
+      lp_amount,
+    )
+  }
+}
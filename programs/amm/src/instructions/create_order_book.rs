@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateOrderBookArgs {
+  /// The smallest order the book takes, in base units
+  pub min_base_amount: u64,
+}
+
+#[derive(Accounts)]
+pub struct CreateOrderBook<'info> {
+  #[account(mut)]
+  pub admin: Signer<'info>,
+  #[account(has_one = admin)]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        init,
+        payer = admin,
+        space = 8 + OrderBook::INIT_SPACE,
+        seeds = [AMM_ORDER_BOOK_SEED_PREFIX, amm.key().as_ref()],
+        bump
+    )]
+  pub order_book: Box<Account<'info, OrderBook>>,
+  pub system_program: Program<'info, System>,
+}
+
+impl CreateOrderBook<'_> {
+  pub fn handle(ctx: Context<Self>, args: CreateOrderBookArgs) -> Result<()> {
+    let CreateOrderBook {
+      admin: _,
+      amm,
+      order_book,
+      system_program: _,
+    } = ctx.accounts;
+
+    require!(!amm.load()?.is_sunset(), AmmError::AmmSunset);
+
+    order_book.set_inner(OrderBook {
+      amm: amm.key(),
+      bump: ctx.bumps.order_book,
+      next_order_id: 0,
+      min_base_amount: args.min_base_amount,
+      bids: vec![],
+      asks: vec![],
+      balances: vec![],
+    });
+
+    Ok(())
+  }
+}
@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::*;
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateRewardPoolArgs {
+  pub reward_per_slot: u64,
+  /// The slot that rewards stop being emitted at
+  pub end_slot: u64,
+}
+
+#[derive(Accounts)]
+pub struct CreateRewardPool<'info> {
+  #[account(mut)]
+  pub admin: Signer<'info>,
+  #[account(
+        has_one = admin,
+        has_one = lp_mint,
+    )]
+  pub amm: AccountLoader<'info, Amm>,
+  pub lp_mint: Box<Account<'info, Mint>>,
+  pub reward_mint: Box<Account<'info, Mint>>,
+  #[account(
+        init,
+        payer = admin,
+        space = 8 + RewardPool::INIT_SPACE,
+        seeds = [
+            REWARD_POOL_SEED_PREFIX,
+            amm.key().as_ref(),
+            reward_mint.key().as_ref()
+        ],
+        bump
+    )]
+  pub reward_pool: Box<Account<'info, RewardPool>>,
+  #[account(
+        init,
+        payer = admin,
+        associated_token::mint = reward_mint,
+        associated_token::authority = reward_pool,
+    )]
+  pub reward_vault: Box<Account<'info, TokenAccount>>,
+  #[account(
+        init,
+        payer = admin,
+        associated_token::mint = lp_mint,
+        associated_token::authority = reward_pool,
+    )]
+  pub stake_vault: Box<Account<'info, TokenAccount>>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub token_program: Program<'info, Token>,
+  pub system_program: Program<'info, System>,
+}
+
+impl CreateRewardPool<'_> {
+  pub fn handle(ctx: Context<Self>, args: CreateRewardPoolArgs) -> Result<()> {
+    let CreateRewardPool {
+      admin: _,
+      amm,
+      lp_mint,
+      reward_mint,
+      reward_pool,
+      reward_vault: _,
+      stake_vault: _,
+      associated_token_program: _,
+      token_program: _,
+      system_program: _,
+    } = ctx.accounts;
+
+    let CreateRewardPoolArgs {
+      reward_per_slot,
+      end_slot,
+    } = args;
+
+    let current_slot = Clock::get()?.slot;
+
+    require!(reward_per_slot > 0, AmmError::InvalidRewardSchedule);
+    require_gt!(end_slot, current_slot, AmmError::InvalidRewardSchedule);
+
+    // keeps the reward-per-share math from overflowing
+    require!(
+      (end_slot - current_slot).checked_mul(reward_per_slot).is_some(),
+      AmmError::InvalidRewardSchedule
+    );
+
+    reward_pool.set_inner(RewardPool {
+      bump: ctx.bumps.reward_pool,
+      amm: amm.key(),
+      lp_mint: lp_mint.key(),
+      reward_mint: reward_mint.key(),
+      reward_per_slot,
+      end_slot,
+      last_update_slot: current_slot,
+      acc_reward_per_share: 0,
+      total_staked: 0,
+      funded_amount: 0,
+      emitted_amount: 0,
+    });
+
+    Ok(())
+  }
+}
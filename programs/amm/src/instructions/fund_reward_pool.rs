@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, *};
+
+use crate::state::*;
+
+#[derive(Debug, Clone, Copy, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
+pub struct FundRewardPoolArgs {
+  /// How many reward tokens to add
+  pub amount: u64,
+}
+
+#[derive(Accounts)]
+pub struct FundRewardPool<'info> {
+  pub funder: Signer<'info>,
+  #[account(mut)]
+  pub reward_pool: Account<'info, RewardPool>,
+  #[account(
+        mut,
+        token::mint = reward_pool.reward_mint,
+        token::authority = funder,
+    )]
+  pub funder_reward_account: Account<'info, TokenAccount>,
+  #[account(
+        mut,
+        associated_token::mint = reward_pool.reward_mint,
+        associated_token::authority = reward_pool,
+    )]
+  pub reward_vault: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+}
+
+impl FundRewardPool<'_> {
+  /// Permissionless, so that anyone can top up a pool's rewards. Only what's
+  /// funded through here counts towards what the pool can emit.
+  pub fn handle(ctx: Context<Self>, args: FundRewardPoolArgs) -> Result<()> {
+    let FundRewardPool {
+      funder,
+      reward_pool,
+      funder_reward_account,
+      reward_vault,
+      token_program,
+    } = ctx.accounts;
+
+    reward_pool.fund(args.amount, Clock::get()?.slot)?;
+
+    token::transfer(
+      CpiContext::new(
+        token_program.to_account_info(),
+        Transfer {
+          from: funder_reward_account.to_account_info(),
+          to: reward_vault.to_account_info(),
+          authority: funder.to_account_info(),
+        },
+      ),
+      args.amount,
+    )
+  }
+}
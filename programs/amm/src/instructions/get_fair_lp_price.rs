@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::state::*;
+
+/// Read-only, so the TWAP's last observation is only as fresh as the last
+/// swap or crank. Callers that need it fresher can `crank_that_twap` first.
+#[derive(Accounts)]
+pub struct GetFairLpPrice<'info> {
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(address = amm.load()?.lp_mint)]
+  pub lp_mint: Account<'info, Mint>,
+}
+
+impl GetFairLpPrice<'_> {
+  /// Returns `Amm::fair_lp_price`, which programs get as return data when
+  /// they call this over CPI.
+  pub fn handle(ctx: Context<Self>) -> Result<u128> {
+    let GetFairLpPrice { amm, lp_mint } = ctx.accounts;
+
+    amm.load()?.fair_lp_price(lp_mint.supply)
+  }
+}
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct MigrateAmm<'info> {
+  /// Tops up the rent for the bigger account. Anyone can migrate a pool,
+  /// since migrating doesn't change anything about it.
+  #[account(mut)]
+  pub payer: Signer<'info>,
+  /// CHECK: a pool in the legacy Borsh layout, which `AccountLoader` can't
+  /// load. `handle` checks its discriminator and size.
+  #[account(mut, owner = crate::ID)]
+  pub amm: UncheckedAccount<'info>,
+  pub system_program: Program<'info, System>,
+}
+
+impl MigrateAmm<'_> {
+  /// Rewrites a pool created before `Amm` was zero-copy into the current
+  /// layout, in place, so that its address, vaults and LP mint stay the same.
+  pub fn handle(ctx: Context<Self>) -> Result<()> {
+    let MigrateAmm {
+      payer,
+      amm,
+      system_program,
+    } = ctx.accounts;
+
+    let legacy_amm = {
+      let data = amm.try_borrow_data()?;
+
+      require!(
+        data.len() >= 8 && data[..8] == Amm::DISCRIMINATOR,
+        ErrorCode::AccountDiscriminatorMismatch
+      );
+
+      // legacy accounts are always smaller than the current layout
+      require_gt!(Amm::SPACE, data.len(), AmmError::AmmAlreadyMigrated);
+
+      LegacyAmm::deserialize(&mut &data[8..]).map_err(|_| ErrorCode::AccountDidNotDeserialize)?
+    };
+
+    let rent_shortfall = Rent::get()?.minimum_balance(Amm::SPACE).saturating_sub(amm.lamports());
+
+    if rent_shortfall > 0 {
+      system_program::transfer(
+        CpiContext::new(
+          system_program.to_account_info(),
+          system_program::Transfer {
+            from: payer.to_account_info(),
+            to: amm.to_account_info(),
+          },
+        ),
+        rent_shortfall,
+      )?;
+    }
+
+    amm.realloc(Amm::SPACE, true)?;
+
+    let mut data = amm.try_borrow_mut_data()?;
+    data[8..].copy_from_slice(bytemuck::bytes_of(&Amm::from(legacy_amm)));
+
+    Ok(())
+  }
+}
@@ -1,13 +1,74 @@
+pub use add_allowlist_entry::*;
 pub use add_liquidity::*;
+pub use add_lp_metadata::*;
+pub use add_referrer::*;
+pub use batch_swap::*;
+pub use cancel_order::*;
+pub use claim_batch_orders::*;
+pub use claim_lp_locker_rewards::*;
+pub use clear_batch::*;
+pub use close_amm::*;
+pub use commit_batch_order::*;
 pub use common::*;
 pub use crank_that_twap::*;
 pub use create_amm::*;
+pub use create_amm_with_liquidity::*;
+pub use create_batch_queue::*;
+pub use create_lp_locker::*;
+pub use create_order_book::*;
+pub use create_reward_pool::*;
+pub use fund_reward_pool::*;
+pub use get_fair_lp_price::*;
+pub use migrate_amm::*;
+pub use place_order::*;
+pub use remove_allowlist_entry::*;
 pub use remove_liquidity::*;
+pub use remove_referrer::*;
+pub use set_gate_authority::*;
+pub use settle_orders::*;
+pub use stake::*;
+pub use stake_lp_locker::*;
+pub use sunset_amm::*;
 pub use swap::*;
+pub use unlock_lp_tokens::*;
+pub use unstake::*;
+pub use widen_circuit_breaker::*;
 
+pub mod add_allowlist_entry;
 pub mod add_liquidity;
+pub mod add_lp_metadata;
+pub mod add_referrer;
+pub mod batch_swap;
+pub mod cancel_order;
+pub mod claim_batch_orders;
+pub mod claim_lp_locker_fees;
+pub mod claim_lp_locker_rewards;
+pub mod claim_rewards;
+pub mod clear_batch;
+pub mod close_amm;
+pub mod commit_batch_order;
 pub mod common;
 pub mod crank_that_twap;
 pub mod create_amm;
+pub mod create_amm_with_liquidity;
+pub mod create_batch_queue;
+pub mod create_lp_locker;
+pub mod create_order_book;
+pub mod create_reward_pool;
+pub mod fund_reward_pool;
+pub mod get_fair_lp_price;
+pub mod migrate_amm;
+pub mod place_order;
+pub mod remove_allowlist_entry;
 pub mod remove_liquidity;
+pub mod remove_referrer;
+pub mod set_gate_authority;
+pub mod settle_orders;
+pub mod stake;
+pub mod stake_lp_locker;
+pub mod sunset_amm;
 pub mod swap;
+pub mod unlock_lp_tokens;
+pub mod unstake;
+pub mod widen_circuit_breaker;
+pub mod withdraw_lp_locker;
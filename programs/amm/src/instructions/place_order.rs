@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+
+use crate::error::AmmError;
+use crate::events::PlaceOrderEvent;
+use crate::*;
+
+#[derive(Debug, Clone, Copy, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
+pub struct PlaceOrderArgs {
+  pub side: OrderSide,
+  /// Quote units per base unit, scaled by 1e12 like the oracle's prices
+  pub price: u128,
+  pub base_amount: u64,
+}
+
+impl ManageOrder<'_> {
+  pub fn handle_place(ctx: Context<Self>, args: PlaceOrderArgs) -> Result<()> {
+    let ManageOrder {
+      user,
+      amm: amm_account,
+      order_book,
+      user_base_account,
+      user_quote_account,
+      vault_ata_base,
+      vault_ata_quote,
+      allowlist_entry,
+      token_program,
+    } = ctx.accounts;
+
+    let PlaceOrderArgs {
+      side,
+      price,
+      base_amount,
+    } = args;
+
+    let mut amm = amm_account.load_mut()?;
+
+    amm.check_allowlisted(
+      amm_account.key(),
+      user.key(),
+      allowlist_entry.as_deref().map(|entry| &**entry),
+    )?;
+
+    require!(!amm.is_sunset(), AmmError::AmmSunset);
+
+    let maker_count = order_book.maker_count();
+    let (order, evicted) = order_book.place(user.key(), side, price, base_amount)?;
+    amm.open_orders += order_book.maker_count() - maker_count;
+
+    let (from, to) = match side {
+      OrderSide::Bid => (user_quote_account, vault_ata_quote),
+      OrderSide::Ask => (user_base_account, vault_ata_base),
+    };
+
+    require_gte!(from.amount, order.escrow_remaining, AmmError::InsufficientBalance);
+
+    token::transfer(
+      CpiContext::new(
+        token_program.to_account_info(),
+        Transfer {
+          from: from.to_account_info(),
+          to: to.to_account_info(),
+          authority: user.to_account_info(),
+        },
+      ),
+      order.escrow_remaining,
+    )?;
+
+    emit!(PlaceOrderEvent {
+      amm: amm_account.key(),
+      user: user.key(),
+      order_id: order.order_id,
+      side,
+      price,
+      base_amount,
+      evicted_order_id: evicted.map(|evicted| evicted.order_id),
+    });
+
+    Ok(())
+  }
+}
@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct RemoveAllowlistEntry<'info> {
+  #[account(mut)]
+  pub gate_authority: Signer<'info>,
+  #[account(
+        constraint = amm.load()?.gate_authority.get() == Some(gate_authority.key()) @ AmmError::PoolNotGated,
+    )]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        mut,
+        has_one = amm,
+        close = gate_authority,
+    )]
+  pub allowlist_entry: Account<'info, AllowlistEntry>,
+}
+
+impl RemoveAllowlistEntry<'_> {
+  /// The user keeps whatever they hold, including LP tokens and resting
+  /// orders, but can't trade or add liquidity until they're allowlisted
+  /// again. Removing liquidity, and cancelling and settling orders, still
+  /// works.
+  pub fn handle(_ctx: Context<Self>) -> Result<()> {
+    Ok(())
+  }
+}
@@ -14,16 +14,31 @@ impl AddOrRemoveLiquidity<'_> {
   pub fn handle_remove(ctx: Context<AddOrRemoveLiquidity>, args: RemoveLiquidityArgs) -> Result<()> {
     let AddOrRemoveLiquidity {
       user,
-      amm,
+      amm: amm_account,
       lp_mint,
       user_lp_account,
       user_base_account,
       user_quote_account,
       vault_ata_base,
       vault_ata_quote,
+      lp_position: _,
+      lp_escrow: _,
+      allowlist_entry: _,
+      native_mint: _,
+      native_sol_account,
+      associated_token_program: _,
       token_program,
+      system_program: _,
     } = ctx.accounts;
 
+    // not gated, so that users taken off the allowlist can still get their
+    // liquidity out. Locked LP tokens are in the position's escrow and can't
+    // be burned here, so this doesn't need to check the lock, see `LpPosition`
+    let mut amm = amm_account.load_mut()?;
+
+    let (user_base_account, user_quote_account) =
+      user_token_accounts(&amm, user_base_account, user_quote_account, native_sol_account)?;
+
     let RemoveLiquidityArgs {
       lp_tokens_to_burn,
       min_quote_amount,
@@ -62,7 +77,11 @@ impl AddOrRemoveLiquidity<'_> {
     amm.base_amount -= base_to_withdraw;
     amm.quote_amount -= quote_to_withdraw;
 
-    let seeds = generate_amm_seeds!(amm);
+    // the pool can't sign with its data borrowed
+    let amm_signer = amm.signer();
+    drop(amm);
+
+    let seeds = generate_amm_seeds!(amm_signer);
 
     for (amount_to_withdraw, from, to) in [
       (base_to_withdraw, vault_ata_base, user_base_account),
@@ -74,7 +93,7 @@ impl AddOrRemoveLiquidity<'_> {
           Transfer {
             from: from.to_account_info(),
             to: to.to_account_info(),
-            authority: amm.to_account_info(),
+            authority: amm_account.to_account_info(),
           },
           &[seeds],
         ),
@@ -82,6 +101,6 @@ impl AddOrRemoveLiquidity<'_> {
       )?;
     }
 
-    Ok(())
+    ctx.accounts.unwrap_native_sol()
   }
 }
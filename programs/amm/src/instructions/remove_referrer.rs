@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct RemoveReferrer<'info> {
+  #[account(mut)]
+  pub admin: Signer<'info>,
+  #[account(has_one = admin)]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        mut,
+        has_one = amm,
+        close = admin,
+    )]
+  pub referrer: Account<'info, Referrer>,
+}
+
+impl RemoveReferrer<'_> {
+  /// Swaps stop paying the referrer from the next one on. What they've
+  /// already been paid stays theirs.
+  pub fn handle(_ctx: Context<Self>) -> Result<()> {
+    Ok(())
+  }
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetGateAuthorityArgs {
+  /// Who can add and remove allowlist entries from now on. Entries added by
+  /// the previous authority stay valid.
+  pub gate_authority: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct SetGateAuthority<'info> {
+  pub admin: Signer<'info>,
+  #[account(
+        mut,
+        has_one = admin,
+    )]
+  pub amm: AccountLoader<'info, Amm>,
+}
+
+impl SetGateAuthority<'_> {
+  /// Only hands a gated pool over to another authority, since ungating a
+  /// pool, or gating an open one, would change who can trade it.
+  pub fn handle(ctx: Context<Self>, args: SetGateAuthorityArgs) -> Result<()> {
+    let SetGateAuthority { admin: _, amm } = ctx.accounts;
+
+    let mut amm = amm.load_mut()?;
+
+    require!(amm.gate_authority.is_some(), AmmError::PoolNotGated);
+
+    amm.gate_authority = Some(args.gate_authority).into();
+
+    Ok(())
+  }
+}
@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::*;
+
+/// Anyone can settle a maker, since the tokens can only go to the maker's
+/// own accounts. That's what frees up the balances of makers without orders.
+#[derive(Accounts)]
+pub struct SettleOrders<'info> {
+  #[account(mut)]
+  pub amm: AccountLoader<'info, Amm>,
+  #[account(
+        mut,
+        has_one = amm,
+    )]
+  pub order_book: Box<Account<'info, OrderBook>>,
+  /// CHECK: only used as the authority of the token accounts paid out to
+  pub maker: UncheckedAccount<'info>,
+  #[account(
+        mut,
+        token::mint = amm.load()?.base_mint,
+        token::authority = maker,
+    )]
+  pub maker_base_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = amm.load()?.quote_mint,
+        token::authority = maker,
+    )]
+  pub maker_quote_account: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.base_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_base: Account<'info, TokenAccount>,
+  #[account(
+        mut,
+        associated_token::mint = amm.load()?.quote_mint,
+        associated_token::authority = amm,
+    )]
+  pub vault_ata_quote: Account<'info, TokenAccount>,
+  pub token_program: Program<'info, Token>,
+}
+
+impl SettleOrders<'_> {
+  /// Pays out what the book owes a maker for their filled and evicted orders.
+  /// Works on sunset pools too.
+  pub fn handle(ctx: Context<Self>) -> Result<()> {
+    let SettleOrders {
+      amm,
+      order_book,
+      maker,
+      maker_base_account,
+      maker_quote_account,
+      vault_ata_base,
+      vault_ata_quote,
+      token_program,
+    } = ctx.accounts;
+
+    let maker_count = order_book.maker_count();
+    let (base_amount, quote_amount) = order_book.settle(maker.key());
+    amm.load_mut()?.open_orders -= maker_count - order_book.maker_count();
+
+    pay_out_from_vaults(
+      amm,
+      token_program,
+      [
+        (base_amount, vault_ata_base, maker_base_account),
+        (quote_amount, vault_ata_quote, maker_quote_account),
+      ],
+    )
+  }
+}
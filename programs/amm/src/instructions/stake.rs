@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+
+use crate::*;
+
+#[derive(Debug, Clone, Copy, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
+pub struct StakeArgs {
+  /// How many LP tokens to stake
+  pub amount: u64,
+}
+
+impl ManageStake<'_> {
+  pub fn handle_stake(ctx: Context<Self>, args: StakeArgs) -> Result<()> {
+    let StakeArgs { amount } = args;
+
+    let ManageStake {
+      user,
+      reward_pool,
+      stake_account,
+      user_lp_account,
+      stake_vault,
+      user_reward_account: _,
+      reward_vault: _,
+      token_program,
+      system_program: _,
+    } = ctx.accounts;
+
+    // a no-op for existing stake accounts
+    stake_account.bump = ctx.bumps.stake_account;
+    stake_account.reward_pool = reward_pool.key();
+    stake_account.owner = user.key();
+
+    reward_pool.stake(stake_account, amount, Clock::get()?.slot)?;
+
+    token::transfer(
+      CpiContext::new(
+        token_program.to_account_info(),
+        Transfer {
+          from: user_lp_account.to_account_info(),
+          to: stake_vault.to_account_info(),
+          authority: user.to_account_info(),
+        },
+      ),
+      amount,
+    )
+  }
+}
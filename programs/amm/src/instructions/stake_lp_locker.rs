@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct StakeLpLocker<'info> {
+  #[account(mut)]
+  pub owner: Signer<'info>,
+  #[account(
+        mut,
+        has_one = owner,
+    )]
+  pub lp_locker: Box<Account<'info, LpLocker>>,
+  #[account(
+        mut,
+        constraint = reward_pool.lp_mint == lp_locker.lp_mint @ AmmError::InvalidLockerRewardAccounts,
+    )]
+  pub reward_pool: Box<Account<'info, RewardPool>>,
+  #[account(
+        init,
+        payer = owner,
+        space = 8 + StakeAccount::INIT_SPACE,
+        seeds = [
+            STAKE_ACCOUNT_SEED_PREFIX,
+            reward_pool.key().as_ref(),
+            lp_locker.key().as_ref()
+        ],
+        bump
+    )]
+  pub stake_account: Box<Account<'info, StakeAccount>>,
+  pub system_program: Program<'info, System>,
+}
+
+impl StakeLpLocker<'_> {
+  /// Counts the locker's LP tokens as staked in a reward pool. They stay in
+  /// the locker, which the program already controls, and leave the stake as
+  /// they're withdrawn or burnt for fees.
+  pub fn handle(ctx: Context<Self>) -> Result<()> {
+    let StakeLpLocker {
+      owner: _,
+      lp_locker,
+      reward_pool,
+      stake_account,
+      system_program: _,
+    } = ctx.accounts;
+
+    require!(lp_locker.reward_pool.is_none(), AmmError::LockerAlreadyStaked);
+
+    lp_locker.reward_pool = Some(reward_pool.key());
+
+    stake_account.set_inner(StakeAccount {
+      bump: ctx.bumps.stake_account,
+      reward_pool: reward_pool.key(),
+      owner: lp_locker.key(),
+      ..StakeAccount::default()
+    });
+
+    reward_pool.stake(stake_account, lp_locker.remaining_amount(), Clock::get()?.slot)
+  }
+}
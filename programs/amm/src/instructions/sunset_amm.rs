@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct SunsetAmm<'info> {
+  pub admin: Signer<'info>,
+  #[account(
+        mut,
+        has_one = admin,
+    )]
+  pub amm: AccountLoader<'info, Amm>,
+}
+
+impl SunsetAmm<'_> {
+  pub fn handle(ctx: Context<Self>) -> Result<()> {
+    let SunsetAmm { admin: _, amm } = ctx.accounts;
+
+    let mut amm = amm.load_mut()?;
+
+    require!(!amm.is_sunset(), AmmError::AmmAlreadySunset);
+
+    let current_slot = Clock::get()?.slot;
+
+    require!(amm.can_sunset(current_slot), AmmError::SunsetBeforeTwapEnd);
+
+    amm.sunset_slot = Some(current_slot).into();
+
+    Ok(())
+  }
+}
@@ -1,15 +1,25 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::spl_token::native_mint;
 use anchor_spl::token::{self, *};
 
 use crate::error::AmmError;
+use crate::events::SwapEvent;
 use crate::generate_amm_seeds;
 use crate::state::*;
+use crate::{native_sol_sides, unwrap_native_sol, user_token_accounts, wrap_native_sol};
+use std::cmp::{max, min};
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct SwapArgs {
   pub swap_type: SwapType,
   pub input_amount: u64,
   pub output_amount_min: u64,
+  /// The most, in basis points, that the swap can move the spot price
+  pub max_price_impact_bps: Option<u16>,
+  /// If set, only the part of `input_amount` that keeps the spot price at or
+  /// below this price when buying, or at or above it when selling, is swapped.
+  /// The rest stays with the user. Uses the same 1e12 scale as the oracle.
+  pub limit_price: Option<u128>,
 }
 
 #[derive(Accounts)]
@@ -17,68 +27,224 @@ pub struct Swap<'info> {
   #[account(mut)]
   pub user: Signer<'info>,
   #[account(mut)]
-  pub amm: Account<'info, Amm>,
+  pub amm: AccountLoader<'info, Amm>,
+  /// Left out on the pool's native mint side when wrapping SOL
   #[account(
         mut,
-        token::mint = amm.base_mint,
+        token::mint = amm.load()?.base_mint,
         token::authority = user,
     )]
-  pub user_base_account: Account<'info, TokenAccount>,
+  pub user_base_account: Option<Box<Account<'info, TokenAccount>>>,
+  /// Left out on the pool's native mint side when wrapping SOL
   #[account(
         mut,
-        token::mint = amm.quote_mint,
+        token::mint = amm.load()?.quote_mint,
         token::authority = user,
     )]
-  pub user_quote_account: Account<'info, TokenAccount>,
+  pub user_quote_account: Option<Box<Account<'info, TokenAccount>>>,
   #[account(
         mut,
-        associated_token::mint = amm.base_mint,
+        associated_token::mint = amm.load()?.base_mint,
         associated_token::authority = amm,
     )]
   pub vault_ata_base: Account<'info, TokenAccount>,
   #[account(
         mut,
-        associated_token::mint = amm.quote_mint,
+        associated_token::mint = amm.load()?.quote_mint,
         associated_token::authority = amm,
     )]
   pub vault_ata_quote: Account<'info, TokenAccount>,
+  /// Receives the referral slice of the fee, must hold the input token
+  #[account(mut)]
+  pub referrer_account: Option<Account<'info, TokenAccount>>,
+  /// Required with `referrer_account`, whose owner the pool's admin has to
+  /// have registered
+  #[account(has_one = amm)]
+  pub referrer: Option<Box<Account<'info, Referrer>>>,
+  /// If passed, resting orders priced better than the curve fill first
+  #[account(
+        mut,
+        has_one = amm,
+    )]
+  pub order_book: Option<Box<Account<'info, OrderBook>>>,
+  /// Required if the pool has a gate authority
+  pub allowlist_entry: Option<Box<Account<'info, AllowlistEntry>>>,
+  /// Required with `native_sol_account`
+  #[account(address = native_mint::ID)]
+  pub native_mint: Option<Box<Account<'info, Mint>>>,
+  /// Pass to swap from, and to, lamports on the pool's native mint side.
+  /// This wSOL account only lives for the swap: it's created here, topped up
+  /// out of the user's lamports when they're the input, and closed back to
+  /// the user after the swap, output and rent included.
+  #[account(
+        init,
+        payer = user,
+        seeds = [NATIVE_SOL_ACCOUNT_SEED_PREFIX, user.key().as_ref()],
+        bump,
+        token::mint = native_mint,
+        token::authority = user,
+    )]
+  pub native_sol_account: Option<Box<Account<'info, TokenAccount>>>,
   pub token_program: Program<'info, Token>,
+  /// Required with `native_sol_account`
+  pub system_program: Option<Program<'info, System>>,
 }
 
 impl Swap<'_> {
   pub fn handle(ctx: Context<Swap>, args: SwapArgs) -> Result<()> {
+    ctx.accounts.execute(args)
+  }
+
+  /// Runs a swap against already validated accounts, shared between `swap`
+  /// and each leg of `batch_swap`.
+  pub fn execute(&mut self, args: SwapArgs) -> Result<()> {
     let Swap {
       user,
-      amm,
+      amm: amm_account,
       user_base_account,
       user_quote_account,
       vault_ata_base,
       vault_ata_quote,
+      referrer_account,
+      referrer,
+      order_book,
+      allowlist_entry,
+      native_mint: _,
+      native_sol_account,
       token_program,
-    } = ctx.accounts;
+      system_program,
+    } = self;
 
     let SwapArgs {
       swap_type,
       input_amount,
       output_amount_min,
+      max_price_impact_bps,
+      limit_price,
     } = args;
 
+    let mut amm = amm_account.load_mut()?;
+
+    if let Some(native_sol_account) = native_sol_account {
+      let (is_base_native, is_quote_native) = native_sol_sides(&amm)?;
+
+      let is_input_native = match swap_type {
+        SwapType::Buy => is_quote_native,
+        SwapType::Sell => is_base_native,
+      };
+
+      if is_input_native {
+        let Some(system_program) = system_program else {
+          return err!(AmmError::MissingSystemProgram);
+        };
+
+        wrap_native_sol(user, native_sol_account, input_amount, system_program, token_program)?;
+      }
+    }
+
+    let (user_base_account, user_quote_account) =
+      user_token_accounts(&amm, user_base_account, user_quote_account, native_sol_account)?;
+
     match swap_type {
       SwapType::Buy => require_gte!(user_quote_account.amount, input_amount, AmmError::InsufficientBalance),
       SwapType::Sell => require_gte!(user_base_account.amount, input_amount, AmmError::InsufficientBalance),
     };
 
+    amm.check_allowlisted(
+      amm_account.key(),
+      user.key(),
+      allowlist_entry.as_deref().map(|entry| &**entry),
+    )?;
+
     require!(input_amount > 0, AmmError::ZeroSwapAmount);
+    require!(!amm.is_sunset(), AmmError::AmmSunset);
+    require!(!amm.is_batch_auction(), AmmError::BatchAuctionOnly);
+
+    let current_slot = Clock::get()?.slot;
 
-    amm.update_twap(Clock::get()?.slot);
+    amm.update_twap(current_slot);
+
+    let referral_fee_bps = match referrer_account {
+      Some(referrer_account) => {
+        let input_mint = match swap_type {
+          SwapType::Buy => amm.quote_mint,
+          SwapType::Sell => amm.base_mint,
+        };
+
+        require_keys_eq!(referrer_account.mint, input_mint, AmmError::InvalidReferrerAccount);
+
+        // otherwise anyone could refer themselves and keep part of the LPs' fee
+        require!(
+          referrer
+            .as_ref()
+            .is_some_and(|referrer| referrer.owner == referrer_account.owner && referrer.owner != user.key()),
+          AmmError::UnregisteredReferrer
+        );
+
+        amm.referral_fee_bps
+      }
+      None => 0,
+    };
 
-    let output_amount = amm.swap(input_amount, swap_type)?;
+    let spot_price_before = amm.spot_price();
 
-    let seeds = generate_amm_seeds!(amm);
+    // Orders only fill where they beat the curve's spot price, which the TWAP
+    // was just updated with, so the TWAP keeps tracking the pool's marginal price.
+    // Book fills don't pay the swap fee, since LPs aren't providing the liquidity.
+    let (book_input_amount, book_output_amount) = match (order_book, spot_price_before) {
+      (Some(order_book), Some(spot_price)) => {
+        let price_bound = match (swap_type, limit_price) {
+          (SwapType::Buy, Some(limit_price)) => min(spot_price, limit_price),
+          (SwapType::Sell, Some(limit_price)) => max(spot_price, limit_price),
+          (_, None) => spot_price,
+        };
+
+        order_book.fill(swap_type, input_amount, price_bound)
+      }
+      _ => (0, 0),
+    };
+
+    let curve_input_amount = input_amount - book_input_amount;
+    let curve_input_amount = match limit_price {
+      Some(limit_price) => amm.max_input_for_limit_price(curve_input_amount, swap_type, referral_fee_bps, limit_price),
+      None => curve_input_amount,
+    };
+
+    let input_amount = book_input_amount + curve_input_amount;
+
+    require!(input_amount > 0, AmmError::LimitPriceReached);
+
+    let SwapResult {
+      output_amount,
+      lp_fee,
+      referral_fee,
+    } = if curve_input_amount > 0 {
+      amm.swap_with_referral(curve_input_amount, swap_type, referral_fee_bps)?
+    } else {
+      SwapResult::default()
+    };
+
+    let output_amount = output_amount + book_output_amount;
+
+    amm
+      .stats
+      .record_swap(swap_type, input_amount, output_amount, lp_fee, current_slot);
+
+    amm.check_circuit_breaker()?;
+
+    if let Some(max_price_impact_bps) = max_price_impact_bps {
+      amm.check_price_impact(spot_price_before, max_price_impact_bps)?;
+    }
+
+    // the pool can't sign with its data borrowed
+    let amm_signer = amm.signer();
+    drop(amm);
+
+    let seeds = generate_amm_seeds!(amm_signer);
 
     let (user_from, vault_to, vault_from, user_to) = match swap_type {
-      SwapType::Buy => (user_quote_account, vault_ata_quote, vault_ata_base, user_base_account),
-      SwapType::Sell => (user_base_account, vault_ata_base, vault_ata_quote, user_quote_account),
+      SwapType::Buy => (&*user_quote_account, vault_ata_quote, vault_ata_base, &*user_base_account),
+      SwapType::Sell => (&*user_base_account, vault_ata_base, vault_ata_quote, &*user_quote_account),
     };
 
     token::transfer(
@@ -90,16 +256,32 @@ impl Swap<'_> {
           authority: user.to_account_info(),
         },
       ),
-      input_amount,
+      input_amount - referral_fee,
     )?;
 
+    if let Some(referrer_account) = referrer_account {
+      if referral_fee > 0 {
+        token::transfer(
+          CpiContext::new(
+            token_program.to_account_info(),
+            Transfer {
+              from: user_from.to_account_info(),
+              to: referrer_account.to_account_info(),
+              authority: user.to_account_info(),
+            },
+          ),
+          referral_fee,
+        )?;
+      }
+    }
+
     token::transfer(
       CpiContext::new_with_signer(
         token_program.to_account_info(),
         Transfer {
           from: vault_from.to_account_info(),
           to: user_to.to_account_info(),
-          authority: amm.to_account_info(),
+          authority: amm_account.to_account_info(),
         },
         &[seeds],
       ),
@@ -108,6 +290,22 @@ impl Swap<'_> {
 
     require_gte!(output_amount, output_amount_min, AmmError::SwapSlippageExceeded);
 
+    emit!(SwapEvent {
+      amm: amm_account.key(),
+      user: user.key(),
+      swap_type,
+      input_amount,
+      output_amount,
+      lp_fee,
+      referral_fee,
+      order_book_input_amount: book_input_amount,
+      referrer: referrer_account.as_ref().map(|referrer_account| referrer_account.key()),
+    });
+
+    if let Some(native_sol_account) = native_sol_account {
+      unwrap_native_sol(user, native_sol_account, token_program)?;
+    }
+
     Ok(())
   }
 }
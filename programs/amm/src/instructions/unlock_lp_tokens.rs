@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+
+use crate::generate_lp_position_seeds;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct UnlockLpTokens<'info> {
+  pub user: Signer<'info>,
+  #[account(has_one = lp_mint)]
+  pub amm: AccountLoader<'info, Amm>,
+  pub lp_mint: Box<Account<'info, Mint>>,
+  #[account(
+        seeds = [
+            LP_POSITION_SEED_PREFIX,
+            amm.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump = lp_position.bump
+    )]
+  pub lp_position: Box<Account<'info, LpPosition>>,
+  #[account(
+        mut,
+        associated_token::mint = lp_mint,
+        associated_token::authority = lp_position,
+    )]
+  pub lp_escrow: Box<Account<'info, TokenAccount>>,
+  #[account(
+        mut,
+        token::mint = lp_mint,
+        token::authority = user,
+    )]
+  pub user_lp_account: Box<Account<'info, TokenAccount>>,
+  pub token_program: Program<'info, Token>,
+}
+
+impl UnlockLpTokens<'_> {
+  /// Moves everything in the position's escrow to the user once the lock
+  /// since their last deposit has passed, or the pool has been sunset.
+  pub fn handle(ctx: Context<Self>) -> Result<()> {
+    let UnlockLpTokens {
+      user: _,
+      amm,
+      lp_mint: _,
+      lp_position,
+      lp_escrow,
+      user_lp_account,
+      token_program,
+    } = ctx.accounts;
+
+    amm.load()?.check_lp_unlocked(lp_position.deposit_slot, Clock::get()?.slot)?;
+
+    let seeds = generate_lp_position_seeds!(lp_position);
+
+    token::transfer(
+      CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Transfer {
+          from: lp_escrow.to_account_info(),
+          to: user_lp_account.to_account_info(),
+          authority: lp_position.to_account_info(),
+        },
+        &[seeds],
+      ),
+      lp_escrow.amount,
+    )
+  }
+}
@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+use crate::*;
+
+#[derive(Debug, Clone, Copy, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
+pub struct UnstakeArgs {
+  /// How many LP tokens to unstake
+  pub amount: u64,
+}
+
+impl ManageStake<'_> {
+  /// Rewards earned up to now stay claimable.
+  pub fn handle_unstake(ctx: Context<Self>, args: UnstakeArgs) -> Result<()> {
+    let UnstakeArgs { amount } = args;
+    let accounts = ctx.accounts;
+
+    accounts
+      .reward_pool
+      .unstake(&mut accounts.stake_account, amount, Clock::get()?.slot)?;
+
+    accounts.pay_out(&accounts.stake_vault, &accounts.user_lp_account, amount)
+  }
+}
@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct WidenCircuitBreakerArgs {
+  /// The new maximum deviation in basis points, or `None` to disable the
+  /// circuit breaker
+  pub circuit_breaker_bps: Option<u16>,
+}
+
+#[derive(Accounts)]
+pub struct WidenCircuitBreaker<'info> {
+  pub admin: Signer<'info>,
+  #[account(
+        mut,
+        has_one = admin,
+    )]
+  pub amm: AccountLoader<'info, Amm>,
+}
+
+impl WidenCircuitBreaker<'_> {
+  pub fn handle(ctx: Context<Self>, args: WidenCircuitBreakerArgs) -> Result<()> {
+    let WidenCircuitBreaker { admin: _, amm } = ctx.accounts;
+
+    let WidenCircuitBreakerArgs { circuit_breaker_bps } = args;
+
+    let mut amm = amm.load_mut()?;
+
+    // tightening is not allowed, otherwise the admin could halt trading
+    // right before a decision deadline
+    let widens = match (amm.circuit_breaker_bps.get(), circuit_breaker_bps) {
+      (_, None) => true,
+      (None, Some(_)) => false,
+      (Some(current_bps), Some(new_bps)) => new_bps >= current_bps,
+    };
+
+    require!(widens, AmmError::CircuitBreakerCanOnlyWiden);
+
+    amm.circuit_breaker_bps = circuit_breaker_bps.into();
+
+    Ok(())
+  }
+}
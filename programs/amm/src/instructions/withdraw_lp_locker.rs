@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Transfer};
+
+use crate::error::AmmError;
+use crate::generate_lp_locker_seeds;
+use crate::*;
+
+impl ManageLpLocker<'_> {
+  /// Withdraws everything that has vested so far, or everything left once
+  /// the pool has been sunset, so that it can be wound down.
+  pub fn handle_withdraw(ctx: Context<Self>) -> Result<()> {
+    let amount = if ctx.accounts.amm.load()?.is_sunset() {
+      ctx.accounts.lp_locker.remaining_amount()
+    } else {
+      ctx.accounts.lp_locker.withdrawable_amount(Clock::get()?.unix_timestamp)
+    };
+
+    require!(amount > 0, AmmError::NothingToWithdraw);
+
+    ctx.accounts.unstake_from_reward_pool(amount)?;
+
+    let ManageLpLocker {
+      amm,
+      lp_locker,
+      locker_vault,
+      owner_lp_account,
+      token_program,
+      ..
+    } = ctx.accounts;
+
+    lp_locker.withdrawn_amount += amount;
+    amm.load_mut()?.locked_lp_tokens -= amount;
+
+    let seeds = generate_lp_locker_seeds!(lp_locker);
+
+    token::transfer(
+      CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Transfer {
+          from: locker_vault.to_account_info(),
+          to: owner_lp_account.to_account_info(),
+          authority: lp_locker.to_account_info(),
+        },
+        &[seeds],
+      ),
+      amount,
+    )
+  }
+}
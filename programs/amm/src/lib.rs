@@ -16,6 +16,7 @@ security_txt! {
 }
 
 pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod state;
 
@@ -26,7 +27,7 @@ declare_id!("CZ6dMWVgnWBS2oETUH2bbgJAJWqT51UaqvAma1DWWP9v");
 
 #[program]
 pub mod amm {
-  use self::add_liquidity::AddLiquidityArgs;
+  use self::add_liquidity::{AddLiquidityArgs, AddLiquidityByBaseArgs};
 
   use super::*;
 
@@ -35,19 +36,153 @@ pub mod amm {
     CreateAmm::handle(ctx, args)
   }
 
+  #[access_control(ctx.accounts.validate())]
+  pub fn create_amm_with_liquidity(ctx: Context<CreateAmmWithLiquidity>, args: CreateAmmWithLiquidityArgs) -> Result<()> {
+    CreateAmmWithLiquidity::handle(ctx, args)
+  }
+
   pub fn add_liquidity(ctx: Context<AddOrRemoveLiquidity>, args: AddLiquidityArgs) -> Result<()> {
     AddOrRemoveLiquidity::handle_add(ctx, args)
   }
 
+  pub fn add_liquidity_by_base(ctx: Context<AddOrRemoveLiquidity>, args: AddLiquidityByBaseArgs) -> Result<()> {
+    AddOrRemoveLiquidity::handle_add_by_base(ctx, args)
+  }
+
   pub fn remove_liquidity(ctx: Context<AddOrRemoveLiquidity>, args: RemoveLiquidityArgs) -> Result<()> {
     AddOrRemoveLiquidity::handle_remove(ctx, args)
   }
 
+  pub fn unlock_lp_tokens(ctx: Context<UnlockLpTokens>) -> Result<()> {
+    UnlockLpTokens::handle(ctx)
+  }
+
   pub fn swap(ctx: Context<Swap>, args: SwapArgs) -> Result<()> {
     Swap::handle(ctx, args)
   }
 
+  pub fn batch_swap<'info>(ctx: Context<'_, '_, 'info, 'info, BatchSwap<'info>>, args: BatchSwapArgs) -> Result<()> {
+    BatchSwap::handle(ctx, args)
+  }
+
+  pub fn create_order_book(ctx: Context<CreateOrderBook>, args: CreateOrderBookArgs) -> Result<()> {
+    CreateOrderBook::handle(ctx, args)
+  }
+
+  pub fn place_order(ctx: Context<ManageOrder>, args: PlaceOrderArgs) -> Result<()> {
+    ManageOrder::handle_place(ctx, args)
+  }
+
+  pub fn cancel_order(ctx: Context<ManageOrder>, args: CancelOrderArgs) -> Result<()> {
+    ManageOrder::handle_cancel(ctx, args)
+  }
+
+  pub fn settle_orders(ctx: Context<SettleOrders>) -> Result<()> {
+    SettleOrders::handle(ctx)
+  }
+
+  pub fn create_batch_queue(ctx: Context<CreateBatchQueue>, args: CreateBatchQueueArgs) -> Result<()> {
+    CreateBatchQueue::handle(ctx, args)
+  }
+
+  pub fn commit_batch_order(ctx: Context<ManageBatchOrder>, args: CommitBatchOrderArgs) -> Result<()> {
+    ManageBatchOrder::handle_commit(ctx, args)
+  }
+
+  pub fn clear_batch(ctx: Context<ClearBatch>) -> Result<()> {
+    ClearBatch::handle(ctx)
+  }
+
+  pub fn claim_batch_orders(ctx: Context<ClaimBatchOrders>) -> Result<()> {
+    ClaimBatchOrders::handle(ctx)
+  }
+
+  pub fn create_reward_pool(ctx: Context<CreateRewardPool>, args: CreateRewardPoolArgs) -> Result<()> {
+    CreateRewardPool::handle(ctx, args)
+  }
+
+  pub fn fund_reward_pool(ctx: Context<FundRewardPool>, args: FundRewardPoolArgs) -> Result<()> {
+    FundRewardPool::handle(ctx, args)
+  }
+
+  pub fn stake(ctx: Context<ManageStake>, args: StakeArgs) -> Result<()> {
+    ManageStake::handle_stake(ctx, args)
+  }
+
+  pub fn unstake(ctx: Context<ManageStake>, args: UnstakeArgs) -> Result<()> {
+    ManageStake::handle_unstake(ctx, args)
+  }
+
+  pub fn claim_rewards(ctx: Context<ManageStake>) -> Result<()> {
+    ManageStake::handle_claim(ctx)
+  }
+
+  pub fn create_lp_locker(ctx: Context<CreateLpLocker>, args: CreateLpLockerArgs) -> Result<()> {
+    CreateLpLocker::handle(ctx, args)
+  }
+
+  pub fn withdraw_lp_locker(ctx: Context<ManageLpLocker>) -> Result<()> {
+    ManageLpLocker::handle_withdraw(ctx)
+  }
+
+  pub fn claim_lp_locker_fees(ctx: Context<ManageLpLocker>) -> Result<()> {
+    ManageLpLocker::handle_claim_fees(ctx)
+  }
+
+  pub fn stake_lp_locker(ctx: Context<StakeLpLocker>) -> Result<()> {
+    StakeLpLocker::handle(ctx)
+  }
+
+  pub fn claim_lp_locker_rewards(ctx: Context<ClaimLpLockerRewards>) -> Result<()> {
+    ClaimLpLockerRewards::handle(ctx)
+  }
+
+  pub fn add_allowlist_entry(ctx: Context<AddAllowlistEntry>, args: AddAllowlistEntryArgs) -> Result<()> {
+    AddAllowlistEntry::handle(ctx, args)
+  }
+
+  pub fn remove_allowlist_entry(ctx: Context<RemoveAllowlistEntry>) -> Result<()> {
+    RemoveAllowlistEntry::handle(ctx)
+  }
+
+  pub fn set_gate_authority(ctx: Context<SetGateAuthority>, args: SetGateAuthorityArgs) -> Result<()> {
+    SetGateAuthority::handle(ctx, args)
+  }
+
+  pub fn add_referrer(ctx: Context<AddReferrer>, args: AddReferrerArgs) -> Result<()> {
+    AddReferrer::handle(ctx, args)
+  }
+
+  pub fn remove_referrer(ctx: Context<RemoveReferrer>) -> Result<()> {
+    RemoveReferrer::handle(ctx)
+  }
+
   pub fn crank_that_twap(ctx: Context<CrankThatTwap>) -> Result<()> {
     CrankThatTwap::handle(ctx)
   }
+
+  pub fn get_fair_lp_price(ctx: Context<GetFairLpPrice>) -> Result<u128> {
+    GetFairLpPrice::handle(ctx)
+  }
+
+  pub fn migrate_amm(ctx: Context<MigrateAmm>) -> Result<()> {
+    MigrateAmm::handle(ctx)
+  }
+
+  pub fn add_lp_metadata(ctx: Context<AddLpMetadata>, args: AddLpMetadataArgs) -> Result<()> {
+    AddLpMetadata::handle(ctx, args)
+  }
+
+  pub fn widen_circuit_breaker(ctx: Context<WidenCircuitBreaker>, args: WidenCircuitBreakerArgs) -> Result<()> {
+    WidenCircuitBreaker::handle(ctx, args)
+  }
+
+  pub fn sunset_amm(ctx: Context<SunsetAmm>) -> Result<()> {
+    SunsetAmm::handle(ctx)
+  }
+
+  #[access_control(ctx.accounts.validate())]
+  pub fn close_amm(ctx: Context<CloseAmm>) -> Result<()> {
+    CloseAmm::handle(ctx)
+  }
 }
@@ -0,0 +1,11 @@
+use anchor_lang::prelude::*;
+
+/// Lets `user` trade in, and provide liquidity to, a pool with a gate
+/// authority. Only the gate authority can create or close one.
+#[account]
+#[derive(Default)]
+pub struct AllowlistEntry {
+  pub bump: u8,
+  pub amm: Pubkey,
+  pub user: Pubkey,
+}
@@ -1,11 +1,16 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::clock::Slot;
+use anchor_lang::Discriminator;
 
 use crate::error::AmmError;
-use crate::{MAX_PRICE, ONE_MINUTE_IN_SLOTS, PRICE_SCALE};
-use std::cmp::{max, min, Ordering};
+use crate::state::{AllowlistEntry, OptionalPubkey, OptionalU128, OptionalU16, OptionalU64};
+use crate::{MAX_PRICE, ONE_MINUTE_IN_SLOTS};
+use amm_math::{price_deviation_bps, AggregatorSnapshot};
+use std::cmp::Ordering;
 
-#[derive(Clone, Copy, Debug, AnchorSerialize, AnchorDeserialize)]
+pub use amm_math::SwapResult;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize, InitSpace)]
 pub enum SwapType {
   /// Swap quote tokens into base tokens
   Buy,
@@ -13,9 +18,21 @@ pub enum SwapType {
   Sell,
 }
 
-#[derive(Default, Clone, Copy, Debug, AnchorDeserialize, AnchorSerialize)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+#[repr(u8)]
+pub enum ObservationChangeMode {
+  /// `max_observation_change_per_update` is a price delta, scaled by 1e12 like prices
+  #[default]
+  Absolute,
+  /// `max_observation_change_per_update` is in basis points of `last_observation`
+  Relative,
+}
+
+/// Laid out by hand with explicit padding, like the `Amm` that it's part of,
+/// so that it's the same on-chain and off.
+#[zero_copy]
+#[derive(Default, Debug)]
 pub struct TwapOracle {
-  pub last_updated_slot: u64,
   /// A price is the number of quote units per base unit multiplied by 1e12.
   /// You cannot simply divide by 1e12 to get a price you can display in the UI
   /// because the base and quote decimals may be different. Instead, do:
@@ -36,91 +53,393 @@ pub struct TwapOracle {
   /// very conservative assumption - META/USDC prices should be between 1e9 and
   /// 1e15, which would overflow after 1e15 years worth of slots.
   ///
-  /// So in the case of an overflow, the aggregator rolls back to 0. It's the
-  /// client's responsibility to sanity check the assets or to handle an
-  /// aggregator at t2 being smaller than an aggregator at t1.
+  /// So in the case of an overflow, the aggregator rolls back to 0 and
+  /// `aggregator_wraps` is incremented. Clients comparing an aggregator at t2
+  /// to an aggregator at t1 should compare the wrap counts too.
   pub aggregator: u128,
-  /// The most that an observation can change per update.
+  /// The most that an observation can change per update, interpreted
+  /// according to `max_observation_change_mode`.
   pub max_observation_change_per_update: u128,
   /// What the initial `latest_observation` is set to.
   pub initial_observation: u128,
+  pub last_updated_slot: u64,
+  /// The slot from which observations start being aggregated. Trading before
+  /// it is ignored, which protects the TWAP from the first, easy to manipulate,
+  /// hours of a market.
+  pub start_slot: u64,
+  /// If set, no observations are recorded after this slot, so that trading
+  /// after a decision deadline doesn't count towards the TWAP.
+  pub end_slot: OptionalU64,
+  /// The number of times `aggregator` has wrapped around. Together they form
+  /// a 192-bit running sum: `aggregator_wraps * 2^128 + aggregator`.
+  ///
+  /// Observations are at most `MAX_PRICE` (< 2^104) and slots at most 2^64,
+  /// so the full sum stays below 2^168 and this can't overflow.
+  pub aggregator_wraps: u64,
+  /// An `ObservationChangeMode`, read through `max_observation_change_mode()`.
+  ///
+  /// Whether `max_observation_change_per_update` is an absolute price delta
+  /// or a percentage of the last observation. An absolute delta suits a market
+  /// whose price stays within an order of magnitude; a relative one keeps
+  /// working when the price moves by several.
+  pub max_observation_change_mode: u8,
+  pub _padding: [u8; 7],
 }
 
 impl TwapOracle {
-  pub fn new(current_slot: Slot, initial_observation: u128, max_observation_change_per_update: u128) -> Self {
+  pub fn new(
+    start_slot: Slot,
+    initial_observation: u128,
+    max_observation_change_per_update: u128,
+    max_observation_change_mode: ObservationChangeMode,
+    end_slot: Option<Slot>,
+  ) -> Self {
     Self {
-      last_updated_slot: current_slot,
+      last_updated_slot: start_slot,
       last_price: 0,
       last_observation: initial_observation,
       aggregator: 0,
       max_observation_change_per_update,
       initial_observation,
+      max_observation_change_mode: max_observation_change_mode as u8,
+      start_slot,
+      end_slot: end_slot.into(),
+      aggregator_wraps: 0,
+      _padding: [0; 7],
+    }
+  }
+
+  pub fn max_observation_change_mode(&self) -> ObservationChangeMode {
+    match self.max_observation_change_mode {
+      0 => ObservationChangeMode::Absolute,
+      _ => ObservationChangeMode::Relative,
+    }
+  }
+
+  /// The aggregator as of the last update, for averaging over any window
+  /// between two snapshots with `amm_math::twap_between`.
+  pub fn aggregator_snapshot(&self) -> AggregatorSnapshot {
+    AggregatorSnapshot {
+      slot: self.last_updated_slot,
+      aggregator: self.aggregator,
+      aggregator_wraps: self.aggregator_wraps,
+    }
+  }
+
+  /// The most that the next observation can move away from `last_observation`.
+  pub fn max_observation_change(&self) -> u128 {
+    match self.max_observation_change_mode() {
+      ObservationChangeMode::Absolute => self.max_observation_change_per_update,
+      ObservationChangeMode::Relative => {
+        amm_math::max_relative_observation_change(self.last_observation, self.max_observation_change_per_update)
+      }
+    }
+  }
+}
+
+/// Lifetime trading statistics, so that dashboards don't have to rebuild
+/// them from transactions.
+///
+/// Every total is cumulative and only changes on a swap or a cleared batch,
+/// so the totals read at any slot from `last_swap_slot` onwards are the
+/// pool's totals as of that slot. Subtracting the totals read at two slots
+/// gives the volume, fees and swap count over the window between them. The
+/// totals already are per-slot accumulators in that sense: weighting them by
+/// slots elapsed, like the TWAP's aggregator, would still take a read at each
+/// end of a window, and would only give the average of the totals over it.
+#[zero_copy]
+#[derive(Default, Debug, PartialEq, Eq, AnchorDeserialize, AnchorSerialize)]
+pub struct AmmStats {
+  /// Base tokens swapped into or out of the pool, through the curve or its
+  /// order book
+  pub base_volume: u128,
+  /// Quote tokens swapped into or out of the pool, through the curve or its
+  /// order book
+  pub quote_volume: u128,
+  /// The swap fees that stayed in the pool for LPs from sells
+  pub base_fees: u128,
+  /// The swap fees that stayed in the pool for LPs from buys
+  pub quote_fees: u128,
+  pub swap_count: u64,
+  /// The slot of the last swap or cleared batch, from which the totals are
+  /// current
+  pub last_swap_slot: u64,
+}
+
+impl AmmStats {
+  /// Adds a swap of `input_amount` into `output_amount`, of which `lp_fee`
+  /// stayed in the pool.
+  pub fn record_swap(&mut self, swap_type: SwapType, input_amount: u64, output_amount: u64, lp_fee: u64, slot: Slot) {
+    let (base_amount, quote_amount) = match swap_type {
+      SwapType::Buy => (output_amount, input_amount),
+      SwapType::Sell => (input_amount, output_amount),
+    };
+
+    self.base_volume += base_amount as u128;
+    self.quote_volume += quote_amount as u128;
+
+    self.record_lp_fee(swap_type, lp_fee);
+
+    self.swap_count += 1;
+    self.last_swap_slot = slot;
+  }
+
+  /// Adds a fee that a swap through the curve left in the pool.
+  pub fn record_lp_fee(&mut self, swap_type: SwapType, lp_fee: u64) {
+    match swap_type {
+      SwapType::Buy => self.quote_fees += lp_fee as u128,
+      SwapType::Sell => self.base_fees += lp_fee as u128,
     }
   }
 }
 
-#[account]
+/// The layout version of `Amm`, bumped whenever its layout changes.
+pub const AMM_VERSION: u8 = 1;
+
+/// A pool. It's zero-copy, so swaps and TWAP cranks read and write it in
+/// place instead of deserializing and reserializing all of it.
+///
+/// The layout is `repr(C)` with every field at an offset that's a multiple
+/// of its alignment and the padding spelled out, so the account is laid out
+/// the same on-chain, where `u128` is 8-byte aligned, and off-chain, where
+/// it's usually 16-byte aligned. `Pod` can't be derived if there's any
+/// implicit padding, so the compiler holds us to that.
+///
+/// Pools created before this layout were Borsh-serialized into a smaller
+/// account, see `LegacyAmm`, and have to go through `migrate_amm` first.
+#[account(zero_copy)]
 #[derive(Default)]
 pub struct Amm {
+  /// `AMM_VERSION` as of when the pool was created or migrated
+  pub version: u8,
   pub bump: u8,
 
-  pub created_at_slot: u64,
+  pub base_mint_decimals: u8,
+  pub quote_mint_decimals: u8,
+
+  /// If set, swaps revert when they would leave the spot price more than this
+  /// many basis points away from the TWAP's last observation.
+  pub circuit_breaker_bps: OptionalU16,
+
+  /// The slice of the swap fee, in basis points of the fee, that goes to a
+  /// swap's referrer when one is given.
+  pub referral_fee_bps: u16,
+
+  pub _padding0: [u8; 6],
+
+  pub oracle: TwapOracle,
+
+  /// Lifetime volume, fees and swap count, updated by every swap and cleared
+  /// batch.
+  pub stats: AmmStats,
+
+  /// The price that the last batch cleared at. In batch-auction mode, this
+  /// rather than the spot price is what the TWAP observes.
+  pub last_clearing_price: OptionalU128,
 
   pub lp_mint: Pubkey,
 
   pub base_mint: Pubkey,
   pub quote_mint: Pubkey,
 
-  pub base_mint_decimals: u8,
-  pub quote_mint_decimals: u8,
+  /// The pool's creator, who can widen the circuit breaker, add LP token
+  /// metadata and sunset the pool.
+  pub admin: Pubkey,
+
+  /// If set, only users with an allowlist entry from this authority can
+  /// swap, place orders, or add liquidity. The admin can hand it over to
+  /// another authority.
+  pub gate_authority: OptionalPubkey,
+
+  pub _padding1: [u8; 7],
+
+  pub created_at_slot: u64,
 
   pub base_amount: u64,
   pub quote_amount: u64,
 
-  pub oracle: TwapOracle,
+  /// The smallest `quote_amount` that the first deposit into the pool can
+  /// have. Here for rounding, since the first depositor gets one LP token per
+  /// quote token.
+  pub min_initial_quote_amount: u64,
+
+  /// How many users the pool's order book and batch queue hold orders for or
+  /// owe tokens to. Their tokens sit in the vaults, so a pool with open
+  /// orders can't be closed.
+  pub open_orders: u64,
+
+  /// How many of the pool's LP tokens are held by LP lockers.
+  pub locked_lp_tokens: u64,
+
+  /// When the admin sunset the pool, if they did. A sunset pool accepts no
+  /// more swaps or deposits, while LPs can still withdraw whenever they like.
+  /// Only possible once the TWAP's window has ended, see `can_sunset`.
+  pub sunset_slot: OptionalU64,
+
+  /// If set, the pool is in batch-auction mode: swaps are committed to its
+  /// batch queue for this many slots, then all cleared at one price.
+  pub batch_window_slots: OptionalU64,
+
+  /// If set, new LP tokens are held in the depositor's `LpPosition` escrow
+  /// until this many slots after their last deposit, so that liquidity can't
+  /// be added just for one large swap's fees.
+  pub lp_lock_slots: OptionalU64,
+
+  /// Zeroed room for new fields, so that adding one doesn't need another
+  /// migration. This also keeps the account bigger than any legacy one.
+  pub _reserved: [u64; 7],
+}
+
+const _: () = assert!(std::mem::size_of::<Amm>() == Amm::SPACE - 8);
+
+/// What a pool signs with, copied out of its account so that the account's
+/// data isn't borrowed while the pool signs a CPI. Pass it to
+/// `generate_amm_seeds!`.
+pub struct AmmSigner {
+  pub base_mint: Pubkey,
+  pub quote_mint: Pubkey,
+  pub bump: u8,
 }
 
 impl Amm {
+  /// The size of a pool's account, discriminator included.
+  pub const SPACE: usize = 8 + 576;
+
+  /// One whole quote token, so $1 if quote is USDC or 1 SOL. Capped for
+  /// mints with more decimals than a u64 can hold a whole token of.
+  pub fn default_min_initial_quote_amount(quote_mint_decimals: u8) -> u64 {
+    10u64.saturating_pow(quote_mint_decimals as u32)
+  }
+
+  /// Reads a pool out of its account's data, for off-chain clients. Unlike
+  /// `AccountDeserialize`, this doesn't need `data` to be aligned for `Amm`,
+  /// which account data fetched over RPC usually isn't.
+  pub fn try_from_account_data(data: &[u8]) -> Result<Amm> {
+    require!(
+      data.len() >= 8 && data[..8] == Amm::DISCRIMINATOR,
+      ErrorCode::AccountDiscriminatorMismatch
+    );
+
+    // legacy pools are smaller, and have to be migrated first
+    require_eq!(data.len(), Amm::SPACE, ErrorCode::AccountDidNotDeserialize);
+
+    Ok(bytemuck::pod_read_unaligned(&data[8..]))
+  }
+
+  pub fn signer(&self) -> AmmSigner {
+    AmmSigner {
+      base_mint: self.base_mint,
+      quote_mint: self.quote_mint,
+      bump: self.bump,
+    }
+  }
+
   pub fn k(&self) -> u128 {
     self.base_amount as u128 * self.quote_amount as u128
   }
 
+  pub fn is_sunset(&self) -> bool {
+    self.sunset_slot.is_some()
+  }
+
+  /// Whether the admin can sunset the pool at `current_slot`. Anyone can
+  /// create a pool and become its admin, so they can't stop trading or
+  /// release LP locks while the TWAP is still deciding anything, the same
+  /// reason the circuit breaker can only be widened. That leaves pools whose
+  /// TWAP has ended.
+  pub fn can_sunset(&self, current_slot: Slot) -> bool {
+    self.oracle.end_slot.get().is_some_and(|end_slot| current_slot > end_slot)
+  }
+
+  /// The price implied by the reserves, in the same form as the oracle's
+  /// prices. `None` if either side of the pool is empty.
+  pub fn spot_price(&self) -> Option<u128> {
+    amm_math::spot_price(self.base_amount, self.quote_amount)
+  }
+
+  pub fn is_batch_auction(&self) -> bool {
+    self.batch_window_slots.is_some()
+  }
+
+  /// The price that the TWAP observes: the spot price, or in batch-auction
+  /// mode the last clearing price, since the spot price between batches
+  /// only reflects part of a batch.
+  pub fn oracle_price(&self) -> Option<u128> {
+    if self.is_batch_auction() {
+      self.last_clearing_price.get()
+    } else {
+      self.spot_price()
+    }
+  }
+
+  /// Errors if the pool is gated and `allowlist_entry` isn't `user`'s entry
+  /// for this pool, whose address is `amm_key`.
+  pub fn check_allowlisted(&self, amm_key: Pubkey, user: Pubkey, allowlist_entry: Option<&AllowlistEntry>) -> Result<()> {
+    if self.gate_authority.is_none() {
+      return Ok(());
+    }
+
+    require!(
+      allowlist_entry.is_some_and(|allowlist_entry| allowlist_entry.amm == amm_key && allowlist_entry.user == user),
+      AmmError::NotAllowlisted
+    );
+
+    Ok(())
+  }
+
+  /// Errors, logging the slot that withdrawals open at, if an LP whose last
+  /// deposit was at `deposit_slot` is still within the lock period. Sunset
+  /// pools don't lock anything, so that LPs can wind them down right away.
+  pub fn check_lp_unlocked(&self, deposit_slot: Slot, current_slot: Slot) -> Result<()> {
+    let Some(lp_lock_slots) = self.lp_lock_slots.get() else {
+      return Ok(());
+    };
+
+    if self.is_sunset() {
+      return Ok(());
+    }
+
+    let unlock_slot = deposit_slot.saturating_add(lp_lock_slots);
+
+    if current_slot < unlock_slot {
+      msg!("LP tokens unlock at slot {}", unlock_slot);
+
+      return err!(AmmError::LpPositionLocked);
+    }
+
+    Ok(())
+  }
+
   /// Does the internal accounting to swap `input_amount` into the returned
   /// output amount so that output amount can be transferred to the user.
   pub fn swap(&mut self, input_amount: u64, swap_type: SwapType) -> Result<u64> {
-    let base_amount_start = self.base_amount as u128;
-    let quote_amount_start = self.quote_amount as u128;
+    Ok(self.swap_with_referral(input_amount, swap_type, 0)?.output_amount)
+  }
 
+  /// Like `swap`, but `referral_fee_bps` of the fee goes to a referrer
+  /// instead of staying in the pool. The referral fee is taken out of the
+  /// input before it reaches the pool's reserves.
+  pub fn swap_with_referral(&mut self, input_amount: u64, swap_type: SwapType, referral_fee_bps: u16) -> Result<SwapResult> {
     let k = self.k();
 
     let (input_reserve, output_reserve) = match swap_type {
-      SwapType::Buy => (quote_amount_start, base_amount_start),
-      SwapType::Sell => (base_amount_start, quote_amount_start),
+      SwapType::Buy => (self.quote_amount, self.base_amount),
+      SwapType::Sell => (self.base_amount, self.quote_amount),
     };
 
-    // airlifted from uniswap v1:
-    // https://github.com/Uniswap/v1-contracts/blob/c10c08d81d6114f694baa8bd32f555a40f6264da/contracts/uniswap_exchange.vy#L106-L111
-
-    require!(input_reserve != 0, AmmError::NoReserves);
-    require!(output_reserve != 0, AmmError::NoReserves);
-
-    let input_amount_with_fee = input_amount as u128 * 99;
+    let swap_result =
+      amm_math::quote_exact_in(input_reserve, output_reserve, input_amount, referral_fee_bps).map_err(AmmError::from)?;
 
-    let numerator = input_amount_with_fee
-      .checked_mul(output_reserve)
-      .ok_or(error!(AmmError::InputAmountOverflow))?;
-
-    let denominator = (input_reserve * 100) + input_amount_with_fee;
-
-    let output_amount = (numerator / denominator).try_into().map_err(|_| AmmError::CastingOverflow)?;
+    let input_amount_to_pool = swap_result.input_amount_to_pool(input_amount);
+    let output_amount = swap_result.output_amount;
 
     match swap_type {
       SwapType::Buy => {
-        self.quote_amount += input_amount;
+        self.quote_amount += input_amount_to_pool;
         self.base_amount -= output_amount;
       }
       SwapType::Sell => {
-        self.base_amount += input_amount;
+        self.base_amount += input_amount_to_pool;
         self.quote_amount -= output_amount;
       }
     }
@@ -129,36 +448,84 @@ impl Amm {
 
     require_gte!(new_k, k, AmmError::ConstantProductInvariantFailed);
 
-    Ok(output_amount)
+    Ok(swap_result)
+  }
+
+  /// Returns the largest part of `input_amount` that can be swapped while
+  /// keeping the spot price at or below `limit_price` when buying, or at or
+  /// above it when selling.
+  pub fn max_input_for_limit_price(&self, input_amount: u64, swap_type: SwapType, referral_fee_bps: u16, limit_price: u128) -> u64 {
+    let (input_reserve, output_reserve) = match swap_type {
+      SwapType::Buy => (self.quote_amount, self.base_amount),
+      SwapType::Sell => (self.base_amount, self.quote_amount),
+    };
+
+    amm_math::max_exact_in_for_price(
+      input_reserve,
+      output_reserve,
+      input_amount,
+      limit_price,
+      swap_type == SwapType::Buy,
+      referral_fee_bps,
+    )
+  }
+
+  /// The square root of k per LP token, scaled by 1e12. Deposits and
+  /// withdrawals leave it unchanged while swap fees grow it, so its growth is
+  /// what each LP token has earned in fees. `None` if there are no LP tokens.
+  pub fn liquidity_per_lp_token(&self, lp_total_supply: u64) -> Option<u128> {
+    amm_math::liquidity_per_lp_token(self.base_amount, self.quote_amount, lp_total_supply)
+  }
+
+  /// What one LP token is worth in quote units, scaled by `PRICE_SCALE`,
+  /// valuing the pool at the TWAP's last observation rather than at its
+  /// reserves.
+  ///
+  /// Reserves can be pushed around within a transaction, but k can't be
+  /// lowered by swaps. So we use the reserves that the pool would have if it
+  /// were arbitraged to the observed price P, which keep the same k: base of
+  /// sqrt(k / P) and quote of sqrt(k * P), worth 2 * sqrt(k * P) in quote.
+  pub fn fair_lp_price(&self, lp_total_supply: u64) -> Result<u128> {
+    let fair_lp_price = amm_math::fair_lp_price(
+      self.base_amount,
+      self.quote_amount,
+      self.oracle.last_observation,
+      lp_total_supply,
+    )
+    .map_err(AmmError::from)?;
+
+    Ok(fair_lp_price)
   }
 
   /// Get the number of base and quote tokens withdrawable from a position
   pub fn get_base_and_quote_withdrawable(&self, lp_tokens: u64, lp_total_supply: u64) -> (u64, u64) {
-    (
-      self.get_base_withdrawable(lp_tokens, lp_total_supply),
-      self.get_quote_withdrawable(lp_tokens, lp_total_supply),
-    )
+    amm_math::withdrawal(self.base_amount, self.quote_amount, lp_tokens, lp_total_supply)
   }
 
   /// Get the number of base tokens withdrawable from a position
   pub fn get_base_withdrawable(&self, lp_tokens: u64, lp_total_supply: u64) -> u64 {
-    // must fit back into u64 since `lp_tokens` <= `lp_total_supply`
-    ((lp_tokens as u128 * self.base_amount as u128) / lp_total_supply as u128) as u64
+    amm_math::withdrawable(self.base_amount, lp_tokens, lp_total_supply)
   }
 
   /// Get the number of quote tokens withdrawable from a position
   pub fn get_quote_withdrawable(&self, lp_tokens: u64, lp_total_supply: u64) -> u64 {
-    ((lp_tokens as u128 * self.quote_amount as u128) / lp_total_supply as u128) as u64
+    amm_math::withdrawable(self.quote_amount, lp_tokens, lp_total_supply)
   }
 
-  /// Returns the time-weighted average price since market creation in UQ64x32 form.
+  /// Returns the time-weighted average price over the oracle's active window,
+  /// from `start_slot` to the last recorded observation, in UQ64x32 form.
   pub fn get_twap(&self) -> Result<u128> {
-    let slots_passed = (self.oracle.last_updated_slot - self.created_at_slot) as u128;
+    let slots_passed = self.oracle.last_updated_slot - self.oracle.start_slot;
 
     require_neq!(slots_passed, 0, AmmError::NoSlotsPassed);
-    assert!(self.oracle.aggregator != 0);
+    assert!(self.oracle.aggregator != 0 || self.oracle.aggregator_wraps != 0);
 
-    Ok(self.oracle.aggregator / slots_passed)
+    let start = AggregatorSnapshot {
+      slot: self.oracle.start_slot,
+      ..AggregatorSnapshot::default()
+    };
+
+    amm_math::twap_between(start, self.oracle.aggregator_snapshot()).ok_or(error!(AmmError::NoSlotsPassed))
   }
 
   /// Updates the TWAP. Should be called before any changes to the AMM's state
@@ -166,6 +533,15 @@ impl Amm {
   ///
   /// Returns an observation if one was recorded.
   pub fn update_twap(&mut self, current_slot: Slot) -> Option<u128> {
+    // we store prices as quote units / base units scaled by 1e12.
+    // for example, suppose META is $100 and there's 400 USDC & 4 META in
+    // this pool. USDC has 6 decimals and META has 9, so we have:
+    // - 400 * 1,000,000   = 400,000,000 USDC units
+    // - 4 * 1,000,000,000 = 4,000,000,000 META units (hansons)
+    // so there's (400,000,000 / 4,000,000,000) or 0.1 USDC units per hanson,
+    // which is 100,000,000,000 when scaled by 1e12.
+    let price = self.oracle_price()?;
+
     let oracle = &mut self.oracle;
     // a manipulator is likely to be "bursty" with their usage, such as a
     // validator who abuses their slots to manipulate the TWAP.
@@ -185,51 +561,45 @@ impl Amm {
     // we allow updates once a minute as a happy medium. if you have an asset
     // that trades near $1500 and you allow $25 updates per minute, it can double
     // over an hour.
-    if current_slot < oracle.last_updated_slot + ONE_MINUTE_IN_SLOTS {
-      return None;
-    }
+    //
+    // `last_updated_slot` starts out as `start_slot`, so this also ignores
+    // anything that happens before the oracle's window opens.
+    //
+    // the first update after `end_slot` closes the window, however soon it
+    // comes after the last one, by aggregating the stretch up to `end_slot`.
+    // it runs before anything else touches the pool, so the price that it
+    // observes is still the one from before the deadline.
+    let end_slot = oracle.end_slot.get();
 
-    if self.base_amount == 0 || self.quote_amount == 0 {
+    if end_slot.is_some_and(|end_slot| oracle.last_updated_slot >= end_slot) {
       return None;
     }
 
-    // we store prices as quote units / base units scaled by 1e12.
-    // for example, suppose META is $100 and there's 400 USDC & 4 META in
-    // this pool. USDC has 6 decimals and META has 9, so we have:
-    // - 400 * 1,000,000   = 400,000,000 USDC units
-    // - 4 * 1,000,000,000 = 4,000,000,000 META units (hansons)
-    // so there's (400,000,000 / 4,000,000,000) or 0.1 USDC units per hanson,
-    // which is 100,000,000,000 when scaled by 1e12.
-    let price = (self.quote_amount as u128 * PRICE_SCALE) / self.base_amount as u128;
-
-    let last_observation = oracle.last_observation;
-
-    let new_observation = if price > last_observation {
-      let max_observation = last_observation.saturating_add(oracle.max_observation_change_per_update);
-
-      min(price, max_observation)
-    } else {
-      let min_observation = last_observation.saturating_sub(oracle.max_observation_change_per_update);
+    let closes_window = end_slot.is_some_and(|end_slot| current_slot > end_slot);
 
-      max(price, min_observation)
-    };
+    if current_slot < oracle.last_updated_slot + ONE_MINUTE_IN_SLOTS && !closes_window {
+      return None;
+    }
 
-    let slot_difference = (current_slot - oracle.last_updated_slot) as u128;
+    let observation_slot = end_slot.map_or(current_slot, |end_slot| current_slot.min(end_slot));
 
-    // if this saturates, the aggregator will wrap back to 0, so this value doesn't
-    // really matter. we just can't panic.
-    let weighted_observation = new_observation.saturating_mul(slot_difference);
+    let new_observation = amm_math::next_observation(oracle.last_observation, price, oracle.max_observation_change());
 
-    let new_aggregator = oracle.aggregator.wrapping_add(weighted_observation);
+    let new_aggregator = oracle.aggregator_snapshot().accumulate(new_observation, observation_slot);
 
     let new_oracle = TwapOracle {
-      last_updated_slot: current_slot,
+      last_updated_slot: observation_slot,
       last_price: price,
       last_observation: new_observation,
-      aggregator: new_aggregator,
-      // these two shouldn't change
+      aggregator: new_aggregator.aggregator,
+      aggregator_wraps: new_aggregator.aggregator_wraps,
+      // these shouldn't change
       max_observation_change_per_update: oracle.max_observation_change_per_update,
       initial_observation: oracle.initial_observation,
+      max_observation_change_mode: oracle.max_observation_change_mode,
+      start_slot: oracle.start_slot,
+      end_slot: oracle.end_slot,
+      _padding: oracle._padding,
     };
 
     assert!(new_oracle.last_updated_slot > oracle.last_updated_slot);
@@ -253,6 +623,53 @@ impl Amm {
     Some(new_observation)
   }
 
+  /// Errors if the spot price is further away from the TWAP's last
+  /// observation than the circuit breaker allows. Should be called after a
+  /// swap has been applied to the AMM's state.
+  pub fn check_circuit_breaker(&self) -> Result<()> {
+    let Some(circuit_breaker_bps) = self.circuit_breaker_bps.get() else {
+      return Ok(());
+    };
+
+    let last_observation = self.oracle.last_observation;
+
+    // nothing to measure against
+    if last_observation == 0 {
+      return Ok(());
+    }
+
+    let Some(spot_price) = self.spot_price() else {
+      return Ok(());
+    };
+
+    require_gte!(
+      circuit_breaker_bps as u128,
+      price_deviation_bps(last_observation, spot_price),
+      AmmError::CircuitBreakerTriggered
+    );
+
+    Ok(())
+  }
+
+  /// Errors, logging the price impact, if the spot price has moved more than
+  /// `max_price_impact_bps` away from `spot_price_before`. Should be called
+  /// after a swap has been applied to the AMM's state.
+  pub fn check_price_impact(&self, spot_price_before: Option<u128>, max_price_impact_bps: u16) -> Result<()> {
+    let (Some(spot_price_before), Some(spot_price_after)) = (spot_price_before, self.spot_price()) else {
+      return Ok(());
+    };
+
+    let price_impact_bps = price_deviation_bps(spot_price_before, spot_price_after);
+
+    if price_impact_bps > max_price_impact_bps as u128 {
+      msg!("Price impact of {} bps", price_impact_bps);
+
+      return err!(AmmError::PriceImpactExceeded);
+    }
+
+    Ok(())
+  }
+
   pub fn invariant(&self) -> Result<()> {
     let oracle = &self.oracle;
 
@@ -277,6 +694,8 @@ macro_rules! generate_amm_seeds {
 
 #[cfg(test)]
 mod simple_amm_tests {
+  use anchor_lang::prelude::Pubkey;
+
   use crate::{error::AmmError, state::*};
   use SwapType::{Buy, Sell};
 
@@ -306,7 +725,7 @@ mod simple_amm_tests {
     assert_eq!(amm.swap(1, Sell).unwrap(), 1);
     assert_eq!(amm.k(), 28); // 4 x 7
 
-    let mut amm_clone = amm.clone();
+    let mut amm_clone = amm;
     // 2 x 14 = 28, but we take one for fee
     assert_eq!(amm.swap(7, Buy).unwrap(), 1);
     assert_eq!(amm.k(), 42); // 3 x 14
@@ -317,12 +736,153 @@ mod simple_amm_tests {
     assert_eq!(amm_clone.k(), 30); // 2 x 15
   }
 
+  #[test]
+  pub fn swap_with_referral() {
+    let amm = Amm {
+      base_amount: 100_000,
+      quote_amount: 100_000,
+      ..Amm::default()
+    };
+
+    let mut without_referral = amm;
+    let output_amount = without_referral.swap(1_000, Buy).unwrap();
+
+    let mut with_referral = amm;
+    // half of the fee
+    let result = with_referral.swap_with_referral(1_000, Buy, 5_000).unwrap();
+
+    // the user gets the same either way
+    assert_eq!(
+      result,
+      SwapResult {
+        output_amount,
+        lp_fee: 5,
+        referral_fee: 5,
+      }
+    );
+
+    // but the pool keeps less of the input
+    assert_eq!(without_referral.quote_amount, 101_000);
+    assert_eq!(with_referral.quote_amount, 100_995);
+    assert_eq!(with_referral.base_amount, without_referral.base_amount);
+    assert!(with_referral.k() > amm.k());
+
+    // the whole fee can go to the referrer
+    let mut all_to_referrer = amm;
+    let result = all_to_referrer.swap_with_referral(1_000, Sell, MAX_BPS).unwrap();
+    assert_eq!(result.lp_fee, 0);
+    assert_eq!(result.referral_fee, 10);
+    assert!(all_to_referrer.k() > amm.k());
+  }
+
+  #[test]
+  pub fn limit_price() {
+    let amm = Amm {
+      base_amount: 1_000_000,
+      quote_amount: 1_000_000,
+      ..Amm::default()
+    };
+
+    // buy until the price is 1.21
+    let limit_price = 1_210_000_000_000;
+    let input_amount = amm.max_input_for_limit_price(1_000_000, Buy, 0, limit_price);
+    // roughly sqrt(1.21) * 1_000_000 - 1_000_000
+    assert!(input_amount > 99_000 && input_amount < 101_000);
+
+    let mut filled = amm;
+    filled.swap(input_amount, Buy).unwrap();
+    assert!(filled.spot_price().unwrap() <= limit_price);
+
+    let mut overfilled = amm;
+    overfilled.swap(input_amount + 1, Buy).unwrap();
+    assert!(overfilled.spot_price().unwrap() > limit_price);
+
+    // sell until the price is 0.81
+    let limit_price = 810_000_000_000;
+    let input_amount = amm.max_input_for_limit_price(1_000_000, Sell, 0, limit_price);
+
+    let mut filled = amm;
+    filled.swap(input_amount, Sell).unwrap();
+    assert!(filled.spot_price().unwrap() >= limit_price);
+
+    let mut overfilled = amm;
+    overfilled.swap(input_amount + 1, Sell).unwrap();
+    assert!(overfilled.spot_price().unwrap() < limit_price);
+
+    // a small enough swap is filled in full
+    assert_eq!(amm.max_input_for_limit_price(1_000, Buy, 0, limit_price * 2), 1_000);
+
+    // and nothing is filled when the price is already past the limit
+    assert_eq!(amm.max_input_for_limit_price(1_000, Buy, 0, PRICE_SCALE / 2), 0);
+    assert_eq!(amm.max_input_for_limit_price(1_000, Sell, 0, PRICE_SCALE * 2), 0);
+  }
+
+  #[test]
+  pub fn circuit_breaker() {
+    let mut amm = Amm {
+      base_amount: 1_000,
+      quote_amount: 1_000,
+      oracle: TwapOracle::new(0, PRICE_SCALE, MAX_PRICE, ObservationChangeMode::Absolute, None),
+      // 5%
+      circuit_breaker_bps: Some(500).into(),
+      ..Amm::default()
+    };
+
+    // moves the price from 1 to ~1.04
+    let mut small_swap = amm;
+    small_swap.swap(20, Buy).unwrap();
+    assert!(small_swap.check_circuit_breaker().is_ok());
+
+    // moves the price from 1 to ~1.21
+    let mut large_swap = amm;
+    large_swap.swap(100, Buy).unwrap();
+    assert_eq!(
+      large_swap.check_circuit_breaker().unwrap_err(),
+      AmmError::CircuitBreakerTriggered.into()
+    );
+
+    // the same goes for the other direction
+    let mut large_sell = amm;
+    large_sell.swap(100, Sell).unwrap();
+    assert_eq!(
+      large_sell.check_circuit_breaker().unwrap_err(),
+      AmmError::CircuitBreakerTriggered.into()
+    );
+
+    amm.circuit_breaker_bps = None.into();
+    amm.swap(100, Buy).unwrap();
+    assert!(amm.check_circuit_breaker().is_ok());
+  }
+
+  #[test]
+  pub fn price_impact() {
+    let amm = Amm {
+      base_amount: 1_000,
+      quote_amount: 1_000,
+      ..Amm::default()
+    };
+
+    let spot_price_before = amm.spot_price();
+
+    // moves the price from 1 to ~1.04
+    let mut swapped = amm;
+    swapped.swap(20, Buy).unwrap();
+    assert!(swapped.check_price_impact(spot_price_before, 500).is_ok());
+    assert_eq!(
+      swapped.check_price_impact(spot_price_before, 300).unwrap_err(),
+      AmmError::PriceImpactExceeded.into()
+    );
+
+    // nothing to measure against
+    assert!(swapped.check_price_impact(None, 0).is_ok());
+  }
+
   #[test]
   pub fn simple_twap_math_amm() {
     let mut amm = Amm {
       base_amount: 5,
       quote_amount: 50,
-      oracle: TwapOracle::new(0, 1_000_000, MAX_PRICE),
+      oracle: TwapOracle::new(0, 1_000_000, MAX_PRICE, ObservationChangeMode::Absolute, None),
       ..Amm::default()
     };
 
@@ -333,16 +893,353 @@ mod simple_amm_tests {
     assert_eq!(amm.update_twap(ONE_MINUTE_IN_SLOTS), Some(10 * PRICE_SCALE));
   }
 
+  #[test]
+  pub fn absolute_observation_change_limit() {
+    let mut amm = Amm {
+      base_amount: 1,
+      quote_amount: 100,
+      oracle: TwapOracle::new(0, 10 * PRICE_SCALE, PRICE_SCALE, ObservationChangeMode::Absolute, None),
+      ..Amm::default()
+    };
+
+    // price is 100, so the observation climbs by 1 per update
+    assert_eq!(amm.update_twap(ONE_MINUTE_IN_SLOTS), Some(11 * PRICE_SCALE));
+    assert_eq!(amm.update_twap(2 * ONE_MINUTE_IN_SLOTS), Some(12 * PRICE_SCALE));
+
+    // and falls by 1 per update
+    amm.quote_amount = 1;
+    assert_eq!(amm.update_twap(3 * ONE_MINUTE_IN_SLOTS), Some(11 * PRICE_SCALE));
+  }
+
+  #[test]
+  pub fn relative_observation_change_limit() {
+    let mut amm = Amm {
+      base_amount: 1,
+      quote_amount: 1_000,
+      // 10%
+      oracle: TwapOracle::new(0, 100 * PRICE_SCALE, 1_000, ObservationChangeMode::Relative, None),
+      ..Amm::default()
+    };
+
+    // price is 1000, so the observation climbs by 10% of itself per update
+    assert_eq!(amm.update_twap(ONE_MINUTE_IN_SLOTS), Some(110 * PRICE_SCALE));
+    assert_eq!(amm.update_twap(2 * ONE_MINUTE_IN_SLOTS), Some(121 * PRICE_SCALE));
+
+    // and falls by 10% of itself per update
+    amm.quote_amount = 1;
+    assert_eq!(amm.update_twap(3 * ONE_MINUTE_IN_SLOTS), Some(108_900_000_000_000));
+
+    // the same setting works when prices are orders of magnitude smaller
+    let mut small_amm = Amm {
+      base_amount: 1_000_000,
+      quote_amount: 1,
+      oracle: TwapOracle::new(0, 1_000, 1_000, ObservationChangeMode::Relative, None),
+      ..Amm::default()
+    };
+
+    assert_eq!(small_amm.update_twap(ONE_MINUTE_IN_SLOTS), Some(1_100));
+  }
+
+  #[test]
+  pub fn relative_observation_change_limit_moves_tiny_observations() {
+    let mut amm = Amm {
+      base_amount: 1,
+      quote_amount: 1,
+      oracle: TwapOracle::new(0, 5, 1, ObservationChangeMode::Relative, None),
+      ..Amm::default()
+    };
+
+    // 1 bps of 5 rounds down to 0, but the observation still moves
+    assert_eq!(amm.update_twap(ONE_MINUTE_IN_SLOTS), Some(6));
+  }
+
+  #[test]
+  pub fn twap_start_delay() {
+    let start_slot = 10 * ONE_MINUTE_IN_SLOTS;
+    let mut amm = Amm {
+      base_amount: 1,
+      quote_amount: 10,
+      oracle: TwapOracle::new(start_slot, 10 * PRICE_SCALE, MAX_PRICE, ObservationChangeMode::Absolute, None),
+      ..Amm::default()
+    };
+
+    // a manipulator moving the price before the window opens achieves nothing
+    amm.quote_amount = 1_000;
+    assert_eq!(amm.update_twap(ONE_MINUTE_IN_SLOTS), None);
+    assert_eq!(amm.update_twap(start_slot), None);
+    assert_eq!(amm.oracle.aggregator, 0);
+    assert_eq!(amm.get_twap().unwrap_err(), AmmError::NoSlotsPassed.into());
+
+    amm.quote_amount = 10;
+    assert_eq!(amm.update_twap(start_slot + ONE_MINUTE_IN_SLOTS), Some(10 * PRICE_SCALE));
+    amm.quote_amount = 30;
+    assert_eq!(amm.update_twap(start_slot + 2 * ONE_MINUTE_IN_SLOTS), Some(30 * PRICE_SCALE));
+
+    // averaged over the two minutes of the active window only
+    assert_eq!(amm.get_twap().unwrap(), 20 * PRICE_SCALE);
+  }
+
+  #[test]
+  pub fn twap_end_slot() {
+    let end_slot = 2 * ONE_MINUTE_IN_SLOTS;
+    let mut amm = Amm {
+      base_amount: 1,
+      quote_amount: 10,
+      oracle: TwapOracle::new(
+        0,
+        10 * PRICE_SCALE,
+        MAX_PRICE,
+        ObservationChangeMode::Absolute,
+        Some(end_slot),
+      ),
+      ..Amm::default()
+    };
+
+    assert_eq!(amm.update_twap(ONE_MINUTE_IN_SLOTS), Some(10 * PRICE_SCALE));
+    assert_eq!(amm.update_twap(end_slot), Some(10 * PRICE_SCALE));
+
+    // trading after the deadline doesn't count
+    amm.quote_amount = 1_000;
+    assert_eq!(amm.update_twap(end_slot + ONE_MINUTE_IN_SLOTS), None);
+    assert_eq!(amm.oracle.last_updated_slot, end_slot);
+    assert_eq!(amm.get_twap().unwrap(), 10 * PRICE_SCALE);
+  }
+
+  #[test]
+  pub fn twap_end_slot_without_updates_in_the_window() {
+    let end_slot = 10 * ONE_MINUTE_IN_SLOTS;
+    let mut amm = Amm {
+      base_amount: 1,
+      quote_amount: 20,
+      oracle: TwapOracle::new(
+        0,
+        10 * PRICE_SCALE,
+        MAX_PRICE,
+        ObservationChangeMode::Absolute,
+        Some(end_slot),
+      ),
+      ..Amm::default()
+    };
+
+    // nobody cranked inside the window, so the first update closes it
+    assert_eq!(amm.update_twap(end_slot + 5 * ONE_MINUTE_IN_SLOTS), Some(20 * PRICE_SCALE));
+    assert_eq!(amm.oracle.last_updated_slot, end_slot);
+    assert_eq!(amm.get_twap().unwrap(), 20 * PRICE_SCALE);
+
+    // and only once
+    amm.quote_amount = 1_000;
+    assert_eq!(amm.update_twap(end_slot + 10 * ONE_MINUTE_IN_SLOTS), None);
+    assert_eq!(amm.get_twap().unwrap(), 20 * PRICE_SCALE);
+  }
+
+  #[test]
+  pub fn twap_end_slot_closes_within_the_rate_limit() {
+    let end_slot = 2 * ONE_MINUTE_IN_SLOTS;
+    let mut amm = Amm {
+      base_amount: 1,
+      quote_amount: 10,
+      oracle: TwapOracle::new(
+        0,
+        10 * PRICE_SCALE,
+        MAX_PRICE,
+        ObservationChangeMode::Absolute,
+        Some(end_slot),
+      ),
+      ..Amm::default()
+    };
+
+    assert_eq!(amm.update_twap(end_slot - 10), Some(10 * PRICE_SCALE));
+
+    // the last 10 slots of the window are aggregated even though a minute
+    // hasn't passed, before a trade after the deadline can move the price
+    assert_eq!(amm.update_twap(end_slot + 1), Some(10 * PRICE_SCALE));
+    assert_eq!(amm.oracle.last_updated_slot, end_slot);
+
+    amm.quote_amount = 1_000;
+    assert_eq!(amm.update_twap(end_slot + ONE_MINUTE_IN_SLOTS), None);
+    assert_eq!(amm.get_twap().unwrap(), 10 * PRICE_SCALE);
+  }
+
+  #[test]
+  pub fn batch_auction_twap_observes_clearing_price() {
+    let mut amm = Amm {
+      base_amount: 1,
+      quote_amount: 10,
+      oracle: TwapOracle::new(0, 0, MAX_PRICE, ObservationChangeMode::Absolute, None),
+      batch_window_slots: Some(10).into(),
+      ..Amm::default()
+    };
+
+    // nothing has cleared yet
+    assert_eq!(amm.update_twap(ONE_MINUTE_IN_SLOTS), None);
+
+    amm.last_clearing_price = Some(7 * PRICE_SCALE).into();
+    assert_eq!(amm.update_twap(ONE_MINUTE_IN_SLOTS), Some(7 * PRICE_SCALE));
+    assert_eq!(amm.oracle.last_price, 7 * PRICE_SCALE);
+  }
+
+  #[test]
+  pub fn liquidity_per_lp_token_grows_with_fees() {
+    let mut amm = Amm {
+      base_amount: 1_000_000,
+      quote_amount: 4_000_000,
+      ..Amm::default()
+    };
+
+    assert_eq!(amm.liquidity_per_lp_token(0), None);
+    assert_eq!(amm.liquidity_per_lp_token(4_000_000), Some(PRICE_SCALE / 2));
+
+    // a withdrawal of a quarter of the pool doesn't change it
+    let (base, quote) = amm.get_base_and_quote_withdrawable(1_000_000, 4_000_000);
+    amm.base_amount -= base;
+    amm.quote_amount -= quote;
+    assert_eq!(amm.liquidity_per_lp_token(3_000_000), Some(PRICE_SCALE / 2));
+
+    amm.swap(100_000, SwapType::Buy).unwrap();
+    assert!(amm.liquidity_per_lp_token(3_000_000).unwrap() > PRICE_SCALE / 2);
+  }
+
+  #[test]
+  pub fn fair_lp_price_ignores_reserve_manipulation() {
+    // 4 quote units per base unit
+    let mut amm = Amm {
+      base_amount: 1_000_000,
+      quote_amount: 4_000_000,
+      oracle: TwapOracle::new(0, 4 * PRICE_SCALE, 0, ObservationChangeMode::Absolute, None),
+      ..Amm::default()
+    };
+
+    assert!(amm.fair_lp_price(0).is_err());
+
+    // the pool is worth 8 million quote units, or 4 per LP token
+    let fair_lp_price = amm.fair_lp_price(2_000_000).unwrap();
+    assert_eq!(fair_lp_price, 4 * PRICE_SCALE);
+
+    // a large buy doubles the reserves' quote side, but the fair price only
+    // grows with the fee it paid
+    amm.swap(4_000_000, SwapType::Buy).unwrap();
+    let spot_lp_value = 2 * amm.quote_amount as u128 * PRICE_SCALE / 2_000_000;
+    assert!(spot_lp_value > 7 * PRICE_SCALE);
+
+    let manipulated_fair_lp_price = amm.fair_lp_price(2_000_000).unwrap();
+    assert!(manipulated_fair_lp_price > fair_lp_price);
+    assert!(manipulated_fair_lp_price < fair_lp_price * 101 / 100);
+  }
+
+  #[test]
+  pub fn allowlist() {
+    let amm_key = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let entry = AllowlistEntry {
+      bump: 0,
+      amm: amm_key,
+      user,
+    };
+    let mut amm = Amm::default();
+
+    assert!(amm.check_allowlisted(amm_key, user, None).is_ok());
+
+    amm.gate_authority = Some(Pubkey::new_unique()).into();
+
+    assert!(amm.check_allowlisted(amm_key, user, None).is_err());
+    assert!(amm.check_allowlisted(amm_key, user, Some(&entry)).is_ok());
+    assert!(amm.check_allowlisted(amm_key, Pubkey::new_unique(), Some(&entry)).is_err());
+    assert!(amm.check_allowlisted(Pubkey::new_unique(), user, Some(&entry)).is_err());
+  }
+
+  #[test]
+  pub fn lp_lock() {
+    let mut amm = Amm::default();
+
+    assert!(amm.check_lp_unlocked(100, 100).is_ok());
+
+    amm.lp_lock_slots = Some(ONE_MINUTE_IN_SLOTS).into();
+
+    assert!(amm.check_lp_unlocked(100, 100).is_err());
+    assert!(amm.check_lp_unlocked(100, 99 + ONE_MINUTE_IN_SLOTS).is_err());
+    assert!(amm.check_lp_unlocked(100, 100 + ONE_MINUTE_IN_SLOTS).is_ok());
+    assert!(amm.check_lp_unlocked(u64::MAX, u64::MAX).is_ok());
+
+    amm.sunset_slot = Some(100).into();
+    assert!(amm.check_lp_unlocked(100, 100).is_ok());
+  }
+
+  #[test]
+  pub fn sunset_only_after_the_twap() {
+    let mut amm = Amm::default();
+
+    // without an end slot, the TWAP never stops mattering
+    assert!(!amm.can_sunset(u64::MAX));
+
+    amm.oracle.end_slot = Some(1_000).into();
+
+    assert!(!amm.can_sunset(999));
+    assert!(!amm.can_sunset(1_000));
+    assert!(amm.can_sunset(1_001));
+  }
+
+  #[test]
+  pub fn layout() {
+    use std::mem::{offset_of, size_of};
+
+    // changing any of these changes the account layout, which needs a new
+    // `AMM_VERSION` and a migration
+    assert_eq!(size_of::<Amm>(), 576);
+    assert_eq!(size_of::<TwapOracle>(), 128);
+    assert_eq!(size_of::<AmmStats>(), 80);
+
+    assert_eq!(offset_of!(Amm, version), 0);
+    assert_eq!(offset_of!(Amm, oracle), 16);
+    assert_eq!(offset_of!(Amm, stats), 144);
+    assert_eq!(offset_of!(Amm, last_clearing_price), 224);
+    assert_eq!(offset_of!(Amm, lp_mint), 256);
+    assert_eq!(offset_of!(Amm, gate_authority), 384);
+    assert_eq!(offset_of!(Amm, created_at_slot), 424);
+    assert_eq!(offset_of!(Amm, sunset_slot), 472);
+    assert_eq!(offset_of!(Amm, _reserved), 520);
+  }
+
+  #[test]
+  pub fn default_min_initial_quote_amount() {
+    assert_eq!(Amm::default_min_initial_quote_amount(0), 1);
+    assert_eq!(Amm::default_min_initial_quote_amount(6), 1_000_000);
+    assert_eq!(Amm::default_min_initial_quote_amount(9), 1_000_000_000);
+    assert_eq!(Amm::default_min_initial_quote_amount(19), 10_000_000_000_000_000_000);
+    assert_eq!(Amm::default_min_initial_quote_amount(20), u64::MAX);
+    assert_eq!(Amm::default_min_initial_quote_amount(u8::MAX), u64::MAX);
+  }
+
+  #[test]
+  pub fn stats() {
+    let mut stats = AmmStats::default();
+
+    stats.record_swap(SwapType::Buy, 1_000, 20, 10, 5);
+    let window_start = stats;
+
+    stats.record_swap(SwapType::Sell, 30, 1_400, 0, 9);
+    stats.record_swap(SwapType::Sell, 100, 4_000, 1, 12);
+
+    assert_eq!(stats.base_volume, 150);
+    assert_eq!(stats.quote_volume, 6_400);
+    assert_eq!(stats.base_fees, 1);
+    assert_eq!(stats.quote_fees, 10);
+    assert_eq!(stats.swap_count, 3);
+    assert_eq!(stats.last_swap_slot, 12);
+
+    // the volume between slots 5 and 12
+    assert_eq!(stats.quote_volume - window_start.quote_volume, 5_400);
+  }
+
   #[test]
   pub fn overflow_twap() {
     let mut amm = Amm {
       base_amount: 1,
       quote_amount: u64::MAX,
-      oracle: TwapOracle::new(0, MAX_PRICE, MAX_PRICE),
+      oracle: TwapOracle::new(0, MAX_PRICE, MAX_PRICE, ObservationChangeMode::Absolute, None),
       ..Amm::default()
     };
 
-    let mut amm_clone = amm.clone();
+    let mut amm_clone = amm;
 
     let slots_until_overflow = u128::MAX / (u64::MAX as u128 * PRICE_SCALE);
 
@@ -350,12 +1247,43 @@ mod simple_amm_tests {
     assert!(amm.oracle.aggregator > MAX_PRICE * 18_400_000);
     assert_ne!(amm.oracle.aggregator, u128::MAX);
 
-    amm_clone.update_twap(slots_until_overflow as u64 + 1);
-    assert_eq!(amm_clone.oracle.aggregator, u128::MAX);
+    assert_eq!(amm.oracle.aggregator_wraps, 0);
+    assert_eq!(amm.get_twap().unwrap(), MAX_PRICE);
 
     // check that it wraps over
+    amm_clone.update_twap(slots_until_overflow as u64 + 1);
+    assert_eq!(amm_clone.oracle.aggregator_wraps, 1);
+    assert_eq!(amm_clone.oracle.aggregator, MAX_PRICE.wrapping_mul(slots_until_overflow + 1));
+    assert_eq!(amm_clone.get_twap().unwrap(), MAX_PRICE);
+
     amm_clone.update_twap(slots_until_overflow as u64 + 1 + ONE_MINUTE_IN_SLOTS);
-    assert_eq!(amm_clone.oracle.aggregator, ONE_MINUTE_IN_SLOTS as u128 * MAX_PRICE - 1);
-    // sub 1 cuz wrap
+    assert_eq!(amm_clone.oracle.aggregator_wraps, 1);
+    assert_eq!(
+      amm_clone.oracle.aggregator,
+      MAX_PRICE.wrapping_mul(slots_until_overflow + 1 + ONE_MINUTE_IN_SLOTS as u128)
+    );
+    assert_eq!(amm_clone.get_twap().unwrap(), MAX_PRICE);
+  }
+
+  #[test]
+  pub fn twap_survives_many_wraps() {
+    let mut amm = Amm {
+      base_amount: 1,
+      quote_amount: u64::MAX,
+      oracle: TwapOracle::new(0, MAX_PRICE, MAX_PRICE, ObservationChangeMode::Absolute, None),
+      ..Amm::default()
+    };
+
+    let half_of_all_slots = u64::MAX / 2;
+
+    // a single huge gap between updates wraps the aggregator many times over
+    amm.update_twap(half_of_all_slots);
+    assert!(amm.oracle.aggregator_wraps > 1 << 38);
+    assert_eq!(amm.get_twap().unwrap(), MAX_PRICE);
+
+    // drop the price for just as long, the average moves halfway down
+    amm.quote_amount = 1;
+    amm.update_twap(half_of_all_slots * 2);
+    assert_eq!(amm.get_twap().unwrap(), (MAX_PRICE + PRICE_SCALE) / 2);
   }
 }
@@ -0,0 +1,573 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::clock::Slot;
+
+use crate::error::AmmError;
+use crate::state::{Amm, SwapResult, SwapType};
+use crate::PRICE_SCALE;
+
+/// How many orders a batch can hold. Orders are taken off the queue once
+/// their batch clears.
+pub const MAX_BATCH_ORDERS: usize = 64;
+
+/// How many users a batch queue can owe tokens to or hold orders for at
+/// once, which leaves room for a full batch's balances to wait to be claimed
+/// while the next batch fills up. Anyone can claim them.
+pub const MAX_BATCH_BALANCES: usize = 2 * MAX_BATCH_ORDERS;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct BatchOrder {
+  pub owner: Pubkey,
+  pub swap_type: SwapType,
+  /// Held in the pool's vaults until the batch clears: quote tokens for
+  /// buys, base tokens for sells
+  pub input_amount: u64,
+  /// If the batch would clear at a price that pays the order less than this,
+  /// it's refunded instead: base tokens for buys, quote tokens for sells
+  pub min_output_amount: u64,
+}
+
+/// What a batch queue owes a user for their cleared orders, until it's
+/// claimed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct BatchBalance {
+  pub owner: Pubkey,
+  pub base_amount: u64,
+  pub quote_amount: u64,
+}
+
+/// The swaps committed to an `Amm` in batch-auction mode. Each window's
+/// swaps all execute at one clearing price, so there's nothing to gain from
+/// ordering swaps within a window, which is what sandwiching relies on.
+#[account]
+#[derive(InitSpace)]
+pub struct BatchQueue {
+  pub amm: Pubkey,
+  pub bump: u8,
+  /// The slot the current window opened in, which is the slot of its
+  /// first order
+  pub window_start_slot: u64,
+  /// The smallest buy, in quote units, set by the pool's admin so that
+  /// filling a batch with dust costs something
+  pub min_quote_amount: u64,
+  /// The smallest sell, in base units
+  pub min_base_amount: u64,
+  #[max_len(MAX_BATCH_ORDERS)]
+  pub orders: Vec<BatchOrder>,
+  /// Every user with an order in the current batch has one
+  #[max_len(MAX_BATCH_BALANCES)]
+  pub balances: Vec<BatchBalance>,
+}
+
+impl BatchQueue {
+  pub fn has_pending_orders(&self) -> bool {
+    !self.orders.is_empty()
+  }
+
+  /// Adds an order to the current batch. The caller escrows its input.
+  pub fn commit(&mut self, order: BatchOrder) -> Result<()> {
+    let min_input_amount = match order.swap_type {
+      SwapType::Buy => self.min_quote_amount,
+      SwapType::Sell => self.min_base_amount,
+    };
+
+    require!(
+      order.input_amount > 0 && order.input_amount >= min_input_amount,
+      AmmError::BatchOrderTooSmall
+    );
+    require_gt!(MAX_BATCH_ORDERS, self.orders.len(), AmmError::BatchQueueFull);
+
+    if !self.balances.iter().any(|balance| balance.owner == order.owner) {
+      require_gt!(MAX_BATCH_BALANCES, self.balances.len(), AmmError::BatchQueueFull);
+
+      self.balances.push(BatchBalance {
+        owner: order.owner,
+        base_amount: 0,
+        quote_amount: 0,
+      });
+    }
+
+    self.orders.push(order);
+
+    Ok(())
+  }
+
+  /// Executes every pending order at one price and returns that price, or
+  /// `None` if nothing executed, in which case the orders are refunded. What
+  /// each order gets goes to its owner's balance, and the queue is emptied.
+  ///
+  /// Buys and sells are first matched against each other, and only the net
+  /// imbalance is swapped through the curve. The clearing price is the
+  /// average price of that curve swap, so neither side of the batch gets a
+  /// better deal than the curve would have given the imbalance.
+  ///
+  /// Orders that would get less than their `min_output_amount` are refunded,
+  /// which moves the price for the rest, so the batch is cleared again
+  /// without them until every order that's left is satisfied.
+  ///
+  /// Each executed order counts as a swap in the pool's stats, and the fee
+  /// of the net swap through the curve as its fees.
+  pub fn clear(&mut self, amm: &mut Amm, slot: Slot) -> Result<Option<u128>> {
+    let mut executes = vec![true; self.orders.len()];
+
+    let (outputs, clearing_price) = loop {
+      let mut cleared_amm = *amm;
+      let (outputs, clearing_price) = self.execute(&mut cleared_amm, &executes, slot)?;
+
+      let mut all_satisfied = true;
+
+      for (index, order) in self.orders.iter().enumerate() {
+        if executes[index] && outputs[index] < order.min_output_amount {
+          executes[index] = false;
+          all_satisfied = false;
+        }
+      }
+
+      if all_satisfied {
+        *amm = cleared_amm;
+        break (outputs, clearing_price);
+      }
+    };
+
+    // a pool without reserves can't execute anything
+    if clearing_price.is_none() {
+      executes.fill(false);
+    }
+
+    for (index, order) in std::mem::take(&mut self.orders).into_iter().enumerate() {
+      let (base_amount, quote_amount) = match (order.swap_type, executes[index]) {
+        (SwapType::Buy, true) => (outputs[index], 0),
+        (SwapType::Sell, true) => (0, outputs[index]),
+        (SwapType::Buy, false) => (0, order.input_amount),
+        (SwapType::Sell, false) => (order.input_amount, 0),
+      };
+
+      self.credit(order.owner, base_amount, quote_amount);
+    }
+
+    Ok(clearing_price)
+  }
+
+  /// Runs the orders that `executes` against `amm`, returning each order's
+  /// output, and the clearing price if anything executed.
+  fn execute(&self, amm: &mut Amm, executes: &[bool], slot: Slot) -> Result<(Vec<u64>, Option<u128>)> {
+    let executing = || {
+      self
+        .orders
+        .iter()
+        .zip(executes)
+        .filter(|(_, executes)| **executes)
+        .map(|(order, _)| order)
+    };
+
+    let total_quote_in: u64 = executing()
+      .filter(|order| order.swap_type == SwapType::Buy)
+      .map(|order| order.input_amount)
+      .sum();
+    let total_base_in: u64 = executing()
+      .filter(|order| order.swap_type == SwapType::Sell)
+      .map(|order| order.input_amount)
+      .sum();
+
+    let mut outputs = vec![0; self.orders.len()];
+
+    let Some(spot_price) = amm.spot_price().filter(|_| total_quote_in > 0 || total_base_in > 0) else {
+      return Ok((outputs, None));
+    };
+
+    let is_net_buy = (total_base_in as u128)
+      .checked_mul(spot_price)
+      .is_some_and(|sells_value| total_quote_in as u128 * PRICE_SCALE >= sells_value);
+
+    let (net_swap_type, net_input, net_counter_input) = if is_net_buy {
+      (SwapType::Buy, total_quote_in, total_base_in)
+    } else {
+      (SwapType::Sell, total_base_in, total_quote_in)
+    };
+
+    let curve_input = net_curve_input(amm, net_swap_type, net_input, net_counter_input);
+    let SwapResult {
+      output_amount: curve_output,
+      lp_fee,
+      ..
+    } = amm.swap_with_referral(curve_input, net_swap_type, 0)?;
+
+    // what the buyers as a whole get in base, and the sellers in quote
+    let (base_to_buyers, quote_to_sellers) = if is_net_buy {
+      (total_base_in + curve_output, total_quote_in - curve_input)
+    } else {
+      (total_base_in - curve_input, total_quote_in + curve_output)
+    };
+
+    for (index, order) in self.orders.iter().enumerate().filter(|(index, _)| executes[*index]) {
+      // pro rata, rounding down so the vaults always cover the claims
+      outputs[index] = match order.swap_type {
+        SwapType::Buy => (order.input_amount as u128 * base_to_buyers as u128 / total_quote_in as u128) as u64,
+        SwapType::Sell => (order.input_amount as u128 * quote_to_sellers as u128 / total_base_in as u128) as u64,
+      };
+
+      amm
+        .stats
+        .record_swap(order.swap_type, order.input_amount, outputs[index], 0, slot);
+    }
+
+    amm.stats.record_lp_fee(net_swap_type, lp_fee);
+
+    let clearing_price = if base_to_buyers > 0 {
+      total_quote_in as u128 * PRICE_SCALE / base_to_buyers as u128
+    } else if total_base_in > 0 {
+      quote_to_sellers as u128 * PRICE_SCALE / total_base_in as u128
+    } else {
+      spot_price
+    };
+
+    Ok((outputs, Some(clearing_price)))
+  }
+
+  /// Takes what the queue owes a user off their balance, returning it as
+  /// (base, quote). The balance itself goes once they have no pending orders.
+  pub fn claim(&mut self, owner: Pubkey) -> (u64, u64) {
+    let has_orders = self.orders.iter().any(|order| order.owner == owner);
+    let Some(index) = self.balances.iter().position(|balance| balance.owner == owner) else {
+      return (0, 0);
+    };
+
+    let balance = &mut self.balances[index];
+    let owed = (balance.base_amount, balance.quote_amount);
+
+    if has_orders {
+      balance.base_amount = 0;
+      balance.quote_amount = 0;
+    } else {
+      self.balances.remove(index);
+    }
+
+    owed
+  }
+
+  /// How many users the queue owes tokens to or holds orders for.
+  pub fn user_count(&self) -> u64 {
+    self.balances.len() as u64
+  }
+
+  /// Adds to a user's balance, which every user with an order already has.
+  fn credit(&mut self, owner: Pubkey, base_amount: u64, quote_amount: u64) {
+    match self.balances.iter_mut().find(|balance| balance.owner == owner) {
+      Some(balance) => {
+        balance.base_amount += base_amount;
+        balance.quote_amount += quote_amount;
+      }
+      None => self.balances.push(BatchBalance {
+        owner,
+        base_amount,
+        quote_amount,
+      }),
+    }
+  }
+}
+
+/// How much of the bigger side of a batch, `input_total`, to swap through
+/// the curve so that the curve's average price is also the price that the
+/// two sides cross at.
+///
+/// For a net buy of `x` quote for `y(x)` base at an average price of
+/// `x / y(x)`, sellers receive `sells * x / y(x)` quote, and what's left of
+/// the buyers' quote has to be exactly what went into the curve:
+/// `x = input_total - sells * x / y(x)`, or `x <= input_total * y / (y + sells)`.
+/// With the curve's `y(x) = 0.99 * x * base / (quote + 0.99 * x)`, that's
+/// linear in `x`: `x <= (99 * input_total * base - 100 * sells * quote) /
+/// (99 * (base + sells))`. A net sell mirrors this.
+///
+/// The output is rounded down to whole units though, so the bound is redone
+/// for the output at that `x`, and then checked against the swap itself.
+fn net_curve_input(amm: &Amm, swap_type: SwapType, input_total: u64, other_side_total: u64) -> u64 {
+  let satisfies = |input: u64| {
+    let output = amm.clone().swap(input, swap_type).unwrap_or(0) as u128;
+
+    (input as u128)
+      .checked_mul(output + other_side_total as u128)
+      .is_some_and(|lhs| lhs <= input_total as u128 * output)
+  };
+
+  let (input_reserve, output_reserve) = match swap_type {
+    SwapType::Buy => (amm.quote_amount, amm.base_amount),
+    SwapType::Sell => (amm.base_amount, amm.quote_amount),
+  };
+
+  let max_input_for_output =
+    |output: u64| (input_total as u128 * output as u128 / (output as u128 + other_side_total as u128)) as u64;
+
+  // the other side's part is below `input_reserve`, so scaling it can't overflow
+  let denominator = output_reserve as u128 + other_side_total as u128;
+  let estimate = (input_total as u128 * output_reserve as u128 / denominator)
+    .saturating_sub(other_side_total as u128 * input_reserve as u128 / denominator * 100 / 99) as u64;
+
+  let estimate = match amm_math::quote_exact_in(input_reserve, output_reserve, estimate, 0) {
+    Ok(swap_result) if swap_result.output_amount > 0 => {
+      let output = swap_result.output_amount;
+
+      // the most that still swaps into `output`
+      let max_input_to_keep_output =
+        amm_math::quote_exact_out(input_reserve, output_reserve, output + 1).map_or(u64::MAX, |input| input - 1);
+
+      max_input_for_output(output).min(max_input_to_keep_output)
+    }
+    _ => estimate,
+  };
+
+  amm_math::largest_satisfying(estimate, input_total, satisfies)
+}
+
+#[cfg(test)]
+mod batch_queue_tests {
+  use super::*;
+
+  fn amm() -> Amm {
+    Amm {
+      base_amount: 1_000_000,
+      quote_amount: 2_000_000,
+      ..Amm::default()
+    }
+  }
+
+  fn queue(orders: &[(SwapType, u64)]) -> BatchQueue {
+    queue_with_min_outputs(
+      &orders
+        .iter()
+        .map(|&(swap_type, input_amount)| (swap_type, input_amount, 0))
+        .collect::<Vec<_>>(),
+    )
+  }
+
+  fn queue_with_min_outputs(orders: &[(SwapType, u64, u64)]) -> BatchQueue {
+    let mut queue = BatchQueue {
+      amm: Pubkey::default(),
+      bump: 0,
+      window_start_slot: 0,
+      min_quote_amount: 0,
+      min_base_amount: 0,
+      orders: vec![],
+      balances: vec![],
+    };
+
+    for &(swap_type, input_amount, min_output_amount) in orders {
+      queue
+        .commit(BatchOrder {
+          owner: Pubkey::new_unique(),
+          swap_type,
+          input_amount,
+          min_output_amount,
+        })
+        .unwrap();
+    }
+
+    queue
+  }
+
+  /// What each order's owner is owed, as (base, quote), in the order they committed.
+  fn owed(queue: &BatchQueue) -> Vec<(u64, u64)> {
+    queue
+      .balances
+      .iter()
+      .map(|balance| (balance.base_amount, balance.quote_amount))
+      .collect()
+  }
+
+  #[test]
+  pub fn one_sided_batch_swaps_everything() {
+    let mut amm = amm();
+    let mut expected_amm = amm;
+    let mut queue = queue(&[(SwapType::Buy, 10_000), (SwapType::Buy, 30_000)]);
+
+    let base_out = expected_amm.swap(40_000, SwapType::Buy).unwrap();
+    let clearing_price = queue.clear(&mut amm, 0).unwrap().unwrap();
+
+    assert_eq!(amm.base_amount, expected_amm.base_amount);
+    assert_eq!(amm.quote_amount, expected_amm.quote_amount);
+    assert_eq!(clearing_price, 40_000 * PRICE_SCALE / base_out as u128);
+
+    // cleared orders are taken off the queue
+    assert!(!queue.has_pending_orders());
+    assert_eq!(owed(&queue), vec![(base_out / 4, 0), (base_out * 3 / 4, 0)]);
+  }
+
+  #[test]
+  pub fn both_sides_clear_at_one_price() {
+    let mut amm = amm();
+    let k = amm.k();
+    let mut queue = queue(&[(SwapType::Buy, 100_000), (SwapType::Sell, 20_000), (SwapType::Sell, 10_000)]);
+
+    let clearing_price = queue.clear(&mut amm, 0).unwrap().unwrap();
+    let owed = owed(&queue);
+
+    // 30,000 base is worth ~60,000 quote, and a bit less than the other
+    // ~40,000 goes through the curve, since the clearing price ends up higher
+    let curve_input = amm.quote_amount - 2_000_000;
+    assert!(curve_input > 38_000 && curve_input < 40_000);
+    assert!(amm.k() >= k);
+
+    // both sides get the same price, up to rounding
+    let buyer_price = 100_000 * PRICE_SCALE / owed[0].0 as u128;
+    let seller_price = owed[1].1 as u128 * PRICE_SCALE / 20_000;
+    assert!(buyer_price.abs_diff(clearing_price) * 10_000 / clearing_price <= 1);
+    assert!(seller_price.abs_diff(clearing_price) * 10_000 / clearing_price <= 1);
+
+    // and the price is at least the curve's, since the net side paid the fee
+    assert!(clearing_price > 2 * PRICE_SCALE);
+
+    let base_claimed: u64 = owed.iter().map(|(base_amount, _)| base_amount).sum();
+    let quote_claimed: u64 = owed.iter().map(|(_, quote_amount)| quote_amount).sum();
+    assert!(base_claimed <= 30_000 + (1_000_000 - amm.base_amount));
+    assert!(quote_claimed <= 100_000 - curve_input);
+  }
+
+  #[test]
+  pub fn clearing_records_stats() {
+    let mut amm = amm();
+    let mut expected_amm = amm;
+    let mut queue = queue(&[(SwapType::Buy, 100_000), (SwapType::Sell, 20_000)]);
+
+    queue.clear(&mut amm, 42).unwrap().unwrap();
+    let owed = owed(&queue);
+
+    // every order counts, at what it put in and got out
+    assert_eq!(amm.stats.base_volume, (owed[0].0 + 20_000) as u128);
+    assert_eq!(amm.stats.quote_volume, (100_000 + owed[1].1) as u128);
+    assert_eq!(amm.stats.swap_count, 2);
+    assert_eq!(amm.stats.last_swap_slot, 42);
+
+    // but only the net buy through the curve paid LPs a fee
+    let curve_input = amm.quote_amount - 2_000_000;
+    let SwapResult { lp_fee, .. } = expected_amm.swap_with_referral(curve_input, SwapType::Buy, 0).unwrap();
+
+    assert_eq!(amm.stats.quote_fees, lp_fee as u128);
+    assert_eq!(amm.stats.base_fees, 0);
+
+    // refunded orders don't count
+    let mut queue = queue_with_min_outputs(&[(SwapType::Buy, 10_000, 10_000)]);
+    queue.clear(&mut amm, 43).unwrap();
+
+    assert_eq!(amm.stats.swap_count, 2);
+    assert_eq!(amm.stats.last_swap_slot, 42);
+  }
+
+  #[test]
+  pub fn net_sell_batch() {
+    let mut amm = amm();
+    let mut queue = queue(&[(SwapType::Buy, 10_000), (SwapType::Sell, 50_000)]);
+
+    let clearing_price = queue.clear(&mut amm, 0).unwrap().unwrap();
+
+    assert!(amm.base_amount > 1_000_000);
+    assert!(clearing_price < 2 * PRICE_SCALE);
+
+    let buyer_price = 10_000 * PRICE_SCALE / owed(&queue)[0].0 as u128;
+    assert!(buyer_price.abs_diff(clearing_price) * 10_000 / clearing_price <= 1);
+  }
+
+  #[test]
+  pub fn orders_below_their_min_output_are_refunded() {
+    let mut amm = amm();
+    let mut expected_amm = amm;
+
+    // the second buy would get ~14,500 base, and wants at least 15,000
+    let mut queue = queue_with_min_outputs(&[(SwapType::Buy, 10_000, 4_000), (SwapType::Buy, 30_000, 15_000)]);
+
+    let base_out = expected_amm.swap(10_000, SwapType::Buy).unwrap();
+    queue.clear(&mut amm, 0).unwrap().unwrap();
+
+    // so only the first one executes, and it gets a better price alone
+    assert_eq!(amm.base_amount, expected_amm.base_amount);
+    assert_eq!(owed(&queue), vec![(base_out, 0), (0, 30_000)]);
+
+    // when nothing can execute, everything is refunded and there's no price
+    let mut queue = queue_with_min_outputs(&[(SwapType::Buy, 10_000, 10_000), (SwapType::Sell, 5_000, 20_000)]);
+    assert_eq!(queue.clear(&mut amm, 0).unwrap(), None);
+    assert_eq!(owed(&queue), vec![(0, 10_000), (5_000, 0)]);
+  }
+
+  #[test]
+  pub fn net_curve_input_is_the_largest_that_crosses() {
+    let satisfies = |amm: &Amm, swap_type, input: u64, input_total: u64, other_side_total: u64| {
+      let output = amm.clone().swap(input, swap_type).unwrap() as u128;
+      input as u128 * (output + other_side_total as u128) <= input_total as u128 * output
+    };
+
+    let pools = [
+      amm(),
+      Amm {
+        base_amount: 1_000,
+        quote_amount: 5_000_000_000,
+        ..Amm::default()
+      },
+    ];
+    let batches = [(100_000, 30_000), (40_000, 0), (1_000_000, 999_999), (5_000_000, 1)];
+
+    for amm in pools {
+      for (swap_type, (input_total, other_side_total)) in [SwapType::Buy, SwapType::Sell]
+        .into_iter()
+        .flat_map(|swap_type| batches.map(|batch| (swap_type, batch)))
+      {
+        let input = net_curve_input(&amm, swap_type, input_total, other_side_total);
+
+        assert!(input == 0 || satisfies(&amm, swap_type, input, input_total, other_side_total));
+        assert!(input == input_total || !satisfies(&amm, swap_type, input + 1, input_total, other_side_total));
+      }
+    }
+  }
+
+  #[test]
+  pub fn empty_pool_refunds() {
+    let mut amm = Amm::default();
+    let mut queue = queue(&[(SwapType::Buy, 10_000), (SwapType::Sell, 50_000)]);
+
+    assert_eq!(queue.clear(&mut amm, 0).unwrap(), None);
+    assert_eq!(owed(&queue), vec![(0, 10_000), (50_000, 0)]);
+  }
+
+  #[test]
+  pub fn small_orders_are_rejected() {
+    let mut queue = queue(&[]);
+    queue.min_quote_amount = 1_000;
+    queue.min_base_amount = 500;
+
+    let order = |swap_type, input_amount| BatchOrder {
+      owner: Pubkey::new_unique(),
+      swap_type,
+      input_amount,
+      min_output_amount: 0,
+    };
+
+    assert!(queue.commit(order(SwapType::Buy, 999)).is_err());
+    assert!(queue.commit(order(SwapType::Sell, 499)).is_err());
+    assert!(queue.commit(order(SwapType::Buy, 1_000)).is_ok());
+    assert!(queue.commit(order(SwapType::Sell, 500)).is_ok());
+  }
+
+  #[test]
+  pub fn claims_keep_the_balances_of_users_with_orders() {
+    let mut amm = amm();
+    let mut queue = queue(&[(SwapType::Buy, 10_000)]);
+    let owner = queue.orders[0].owner;
+
+    queue.clear(&mut amm, 0).unwrap();
+    queue
+      .commit(BatchOrder {
+        owner,
+        swap_type: SwapType::Sell,
+        input_amount: 1_000,
+        min_output_amount: 0,
+      })
+      .unwrap();
+
+    let (base_amount, _) = owed(&queue)[0];
+    assert_eq!(queue.claim(owner), (base_amount, 0));
+    assert_eq!(queue.user_count(), 1);
+    assert!(queue.has_pending_orders());
+
+    queue.clear(&mut amm, 0).unwrap();
+    let (_, quote_amount) = owed(&queue)[0];
+    assert_eq!(queue.claim(owner), (0, quote_amount));
+    assert_eq!(queue.user_count(), 0);
+    assert_eq!(queue.claim(owner), (0, 0));
+  }
+}
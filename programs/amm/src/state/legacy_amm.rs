@@ -0,0 +1,185 @@
+use anchor_lang::prelude::*;
+
+use super::*;
+
+/// `TwapOracle` as it was stored before `Amm` moved to a zero-copy layout.
+#[derive(Default, Clone, Copy, Debug, AnchorDeserialize, AnchorSerialize)]
+pub struct LegacyTwapOracle {
+  pub last_updated_slot: u64,
+  pub last_price: u128,
+  pub last_observation: u128,
+  pub aggregator: u128,
+  pub max_observation_change_per_update: u128,
+  pub initial_observation: u128,
+}
+
+/// `Amm` as it was stored before it moved to a zero-copy layout: Borsh, in
+/// an account of `8 + size_of` the old in-memory struct, which is 8 + 224
+/// bytes where `u128` is 16-byte aligned. Borsh only fills 211 of them and
+/// leaves the rest zeroed. Only `migrate_amm` reads it, so the field order
+/// here has to match the old struct exactly.
+#[derive(Default, Clone, Copy, Debug, AnchorDeserialize, AnchorSerialize)]
+pub struct LegacyAmm {
+  pub bump: u8,
+  pub created_at_slot: u64,
+  pub lp_mint: Pubkey,
+  pub base_mint: Pubkey,
+  pub quote_mint: Pubkey,
+  pub base_mint_decimals: u8,
+  pub quote_mint_decimals: u8,
+  pub base_amount: u64,
+  pub quote_amount: u64,
+  pub oracle: LegacyTwapOracle,
+}
+
+/// What legacy pools required of their first deposit, in quote units.
+pub const LEGACY_MIN_INITIAL_QUOTE_AMOUNT: u64 = 100_000_000;
+
+/// Legacy pools predate everything that came after the TWAP, so those fields
+/// get the values that keep the pool working the way it did:
+/// - the TWAP limits absolute observation changes and aggregates from the
+///   pool's creation, with no end
+/// - the admin is the default pubkey, so there isn't one, since nobody was
+///   given admin rights over the pool when it was created
+/// - the first deposit still needs `LEGACY_MIN_INITIAL_QUOTE_AMOUNT`
+/// - there's no circuit breaker, referral fee, batch auction, LP lock or
+///   gate, and no stats from before the migration
+impl From<LegacyAmm> for Amm {
+  fn from(amm: LegacyAmm) -> Self {
+    let LegacyAmm {
+      bump,
+      created_at_slot,
+      lp_mint,
+      base_mint,
+      quote_mint,
+      base_mint_decimals,
+      quote_mint_decimals,
+      base_amount,
+      quote_amount,
+      oracle:
+        LegacyTwapOracle {
+          last_updated_slot,
+          last_price,
+          last_observation,
+          aggregator,
+          max_observation_change_per_update,
+          initial_observation,
+        },
+    } = amm;
+
+    Amm {
+      version: AMM_VERSION,
+      bump,
+      created_at_slot,
+      lp_mint,
+      base_mint,
+      quote_mint,
+      base_mint_decimals,
+      quote_mint_decimals,
+      base_amount,
+      quote_amount,
+      oracle: TwapOracle {
+        last_updated_slot,
+        last_price,
+        last_observation,
+        aggregator,
+        max_observation_change_per_update,
+        initial_observation,
+        max_observation_change_mode: ObservationChangeMode::Absolute as u8,
+        start_slot: created_at_slot,
+        end_slot: None.into(),
+        aggregator_wraps: 0,
+        ..TwapOracle::default()
+      },
+      admin: Pubkey::default(),
+      circuit_breaker_bps: None.into(),
+      min_initial_quote_amount: LEGACY_MIN_INITIAL_QUOTE_AMOUNT,
+      sunset_slot: None.into(),
+      referral_fee_bps: 0,
+      open_orders: 0,
+      batch_window_slots: None.into(),
+      last_clearing_price: None.into(),
+      lp_lock_slots: None.into(),
+      locked_lp_tokens: 0,
+      gate_authority: None.into(),
+      stats: AmmStats::default(),
+      ..Amm::default()
+    }
+  }
+}
+
+#[cfg(test)]
+mod legacy_amm_tests {
+  use super::*;
+  use anchor_lang::Discriminator;
+
+  #[test]
+  pub fn migrates_a_baseline_pool() {
+    let (lp_mint, base_mint, quote_mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+    // a pool's account as the program stored it before `Amm` was zero-copy
+    let mut data = [
+      &Amm::DISCRIMINATOR[..],
+      &[254],
+      &10u64.to_le_bytes(),
+      lp_mint.as_ref(),
+      base_mint.as_ref(),
+      quote_mint.as_ref(),
+      &[9, 6],
+      &1_000u64.to_le_bytes(),
+      &2_000u64.to_le_bytes(),
+      // the TWAP oracle
+      &300u64.to_le_bytes(),
+      &(2 * PRICE_SCALE).to_le_bytes(),
+      &PRICE_SCALE.to_le_bytes(),
+      &u128::MAX.to_le_bytes(),
+      &100u128.to_le_bytes(),
+      &(PRICE_SCALE / 2).to_le_bytes(),
+    ]
+    .concat();
+
+    assert_eq!(data.len(), 8 + 211);
+
+    // Borsh doesn't pad, so the rest of the account was left zeroed
+    data.resize(8 + 224, 0);
+    assert!(data.len() < Amm::SPACE);
+
+    // as `migrate_amm` reads it
+    let amm = Amm::from(LegacyAmm::deserialize(&mut &data[8..]).unwrap());
+
+    assert_eq!(amm.version, AMM_VERSION);
+    assert_eq!(amm.bump, 254);
+    assert_eq!(amm.created_at_slot, 10);
+    assert_eq!((amm.lp_mint, amm.base_mint, amm.quote_mint), (lp_mint, base_mint, quote_mint));
+    assert_eq!((amm.base_mint_decimals, amm.quote_mint_decimals), (9, 6));
+    assert_eq!((amm.base_amount, amm.quote_amount), (1_000, 2_000));
+
+    let oracle = amm.oracle;
+    assert_eq!(oracle.last_updated_slot, 300);
+    assert_eq!(oracle.last_price, 2 * PRICE_SCALE);
+    assert_eq!(oracle.last_observation, PRICE_SCALE);
+    assert_eq!(oracle.aggregator, u128::MAX);
+    assert_eq!(oracle.max_observation_change_per_update, 100);
+    assert_eq!(oracle.initial_observation, PRICE_SCALE / 2);
+    assert_eq!(oracle.max_observation_change_mode(), ObservationChangeMode::Absolute);
+    assert_eq!(oracle.start_slot, 10);
+    assert_eq!(oracle.end_slot.get(), None);
+    assert_eq!(oracle.aggregator_wraps, 0);
+
+    assert_eq!(amm.admin, Pubkey::default());
+    assert_eq!(amm.circuit_breaker_bps.get(), None);
+    assert_eq!(amm.min_initial_quote_amount, LEGACY_MIN_INITIAL_QUOTE_AMOUNT);
+    assert_eq!(amm.sunset_slot.get(), None);
+    assert_eq!(amm.referral_fee_bps, 0);
+    assert_eq!(amm.open_orders, 0);
+    assert!(!amm.is_batch_auction());
+    assert_eq!(amm.last_clearing_price.get(), None);
+    assert_eq!(amm.lp_lock_slots.get(), None);
+    assert_eq!(amm.locked_lp_tokens, 0);
+    assert_eq!(amm.gate_authority.get(), None);
+    assert_eq!(amm.stats, AmmStats::default());
+
+    // the TWAP reads the same as it did, from the pool's creation
+    assert_eq!(amm.get_twap().unwrap(), u128::MAX / 290);
+  }
+}
@@ -0,0 +1,149 @@
+use anchor_lang::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub enum LockerVesting {
+  /// Everything unlocks at `end_timestamp`
+  Cliff,
+  /// Unlocks linearly from `start_timestamp` to `end_timestamp`
+  Linear,
+}
+
+/// LP tokens escrowed by the program so that anyone can check that a pool's
+/// liquidity is locked. The owner still earns the locked tokens' share of
+/// swap fees, through `claim_lp_locker_fees`, and of a reward pool's
+/// emissions, through `stake_lp_locker`.
+#[account]
+pub struct LpLocker {
+  pub bump: u8,
+  pub amm: Pubkey,
+  pub lp_mint: Pubkey,
+  pub owner: Pubkey,
+  /// Lets an owner have several lockers on the same pool
+  pub locker_id: u64,
+  pub vesting: LockerVesting,
+  pub start_timestamp: i64,
+  pub end_timestamp: i64,
+  /// The LP tokens that vest over the schedule: what was locked, less what
+  /// has been burnt to pay out fees
+  pub total_amount: u64,
+  pub withdrawn_amount: u64,
+  /// `Amm::liquidity_per_lp_token` as of the last fee claim
+  pub liquidity_per_lp_token_claimed: u128,
+  /// The reward pool that counts this locker's LP tokens as staked, if any
+  pub reward_pool: Option<Pubkey>,
+}
+
+impl LpLocker {
+  /// The LP tokens still held by the locker.
+  pub fn remaining_amount(&self) -> u64 {
+    self.total_amount - self.withdrawn_amount
+  }
+
+  /// How many LP tokens the owner can withdraw at `timestamp`.
+  pub fn withdrawable_amount(&self, timestamp: i64) -> u64 {
+    let vested_amount = match self.vesting {
+      LockerVesting::Cliff if timestamp >= self.end_timestamp => self.total_amount,
+      LockerVesting::Cliff => 0,
+      LockerVesting::Linear if timestamp >= self.end_timestamp => self.total_amount,
+      LockerVesting::Linear if timestamp <= self.start_timestamp => 0,
+      LockerVesting::Linear => {
+        let elapsed = (timestamp - self.start_timestamp) as u128;
+        let duration = (self.end_timestamp - self.start_timestamp) as u128;
+
+        (self.total_amount as u128 * elapsed / duration) as u64
+      }
+    };
+
+    // burning for fees shrinks `total_amount`, which can briefly put the
+    // vested amount below what's been withdrawn
+    vested_amount.saturating_sub(self.withdrawn_amount)
+  }
+
+  /// How many LP tokens to burn so that the owner gets the fees the locker
+  /// has earned since the last claim, while what's left keeps the liquidity
+  /// it had then.
+  pub fn fee_lp_tokens(&self, liquidity_per_lp_token: u128) -> u64 {
+    if liquidity_per_lp_token <= self.liquidity_per_lp_token_claimed {
+      return 0;
+    }
+
+    let growth = liquidity_per_lp_token - self.liquidity_per_lp_token_claimed;
+
+    (self.remaining_amount() as u128 * growth / liquidity_per_lp_token) as u64
+  }
+}
+
+#[macro_export]
+macro_rules! generate_lp_locker_seeds {
+  ($lp_locker:expr) => {{
+    &[
+      LP_LOCKER_SEED_PREFIX,
+      $lp_locker.amm.as_ref(),
+      $lp_locker.owner.as_ref(),
+      &$lp_locker.locker_id.to_le_bytes(),
+      &[$lp_locker.bump],
+    ]
+  }};
+}
+
+#[cfg(test)]
+mod lp_locker_tests {
+  use super::*;
+
+  fn locker(vesting: LockerVesting) -> LpLocker {
+    LpLocker {
+      bump: 0,
+      amm: Pubkey::default(),
+      lp_mint: Pubkey::default(),
+      owner: Pubkey::default(),
+      locker_id: 0,
+      vesting,
+      start_timestamp: 1_000,
+      end_timestamp: 2_000,
+      total_amount: 500,
+      withdrawn_amount: 0,
+      liquidity_per_lp_token_claimed: 100,
+      reward_pool: None,
+    }
+  }
+
+  #[test]
+  pub fn cliff_vesting() {
+    let locker = locker(LockerVesting::Cliff);
+
+    assert_eq!(locker.withdrawable_amount(1_500), 0);
+    assert_eq!(locker.withdrawable_amount(1_999), 0);
+    assert_eq!(locker.withdrawable_amount(2_000), 500);
+  }
+
+  #[test]
+  pub fn linear_vesting() {
+    let mut locker = locker(LockerVesting::Linear);
+
+    assert_eq!(locker.withdrawable_amount(0), 0);
+    assert_eq!(locker.withdrawable_amount(1_500), 250);
+
+    locker.withdrawn_amount = 250;
+    assert_eq!(locker.withdrawable_amount(1_500), 0);
+    assert_eq!(locker.withdrawable_amount(1_600), 50);
+    assert_eq!(locker.withdrawable_amount(i64::MAX), 250);
+
+    // after burning for fees
+    locker.total_amount = 400;
+    assert_eq!(locker.withdrawable_amount(1_600), 0);
+  }
+
+  #[test]
+  pub fn fee_lp_tokens() {
+    let mut locker = locker(LockerVesting::Cliff);
+
+    assert_eq!(locker.fee_lp_tokens(100), 0);
+    assert_eq!(locker.fee_lp_tokens(90), 0);
+
+    // 25% more liquidity per token means a fifth of the tokens are fees
+    assert_eq!(locker.fee_lp_tokens(125), 100);
+
+    locker.withdrawn_amount = 100;
+    assert_eq!(locker.fee_lp_tokens(125), 80);
+  }
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+/// Tracks when a user last added liquidity to a pool, for pools with
+/// `lp_lock_slots`. On those pools, new LP tokens aren't minted to the user
+/// but to the position's escrow, the position's associated token account,
+/// where only `unlock_lp_tokens` can move them out once the lock has passed.
+/// So the lock is on the tokens themselves, and can't be sidestepped by
+/// moving them to another wallet.
+///
+/// Rejecting early withdrawals in `remove_liquidity` instead can't work: it
+/// could only look up the position of whoever burns the LP tokens, and the
+/// depositor can just transfer them to a wallet with no recent deposit and
+/// withdraw from there.
+#[account]
+#[derive(Default, InitSpace)]
+pub struct LpPosition {
+  pub amm: Pubkey,
+  pub owner: Pubkey,
+  /// The slot of the owner's latest deposit, which relocks everything in the
+  /// escrow
+  pub deposit_slot: u64,
+  pub bump: u8,
+}
+
+#[macro_export]
+macro_rules! generate_lp_position_seeds {
+  ($lp_position:expr) => {{
+    &[
+      LP_POSITION_SEED_PREFIX,
+      $lp_position.amm.as_ref(),
+      $lp_position.owner.as_ref(),
+      &[$lp_position.bump],
+    ]
+  }};
+}
@@ -1,11 +1,37 @@
+pub use allowlist_entry::*;
 pub use amm::*;
+pub use batch_queue::*;
+pub use legacy_amm::*;
+pub use lp_locker::*;
+pub use lp_position::*;
+pub use order_book::*;
+pub use pod_option::*;
+pub use referrer::*;
+pub use reward_pool::*;
 
+pub mod allowlist_entry;
 pub mod amm;
+pub mod batch_queue;
+pub mod legacy_amm;
+pub mod lp_locker;
+pub mod lp_position;
+pub mod order_book;
+pub mod pod_option;
+pub mod referrer;
+pub mod reward_pool;
 
 pub const TEN_SECONDS_IN_SLOTS: u64 = 25;
 pub const ONE_MINUTE_IN_SLOTS: u64 = TEN_SECONDS_IN_SLOTS * 6;
-pub const PRICE_SCALE: u128 = 1_000_000_000_000;
-pub const MAX_PRICE: u128 = u64::MAX as u128 * PRICE_SCALE;
+pub use amm_math::{MAX_BPS, MAX_PRICE, PRICE_SCALE};
 
 pub const AMM_SEED_PREFIX: &[u8] = b"amm__";
 pub const AMM_LP_MINT_SEED_PREFIX: &[u8] = b"amm_lp_mint";
+pub const AMM_ORDER_BOOK_SEED_PREFIX: &[u8] = b"amm_order_book";
+pub const AMM_BATCH_QUEUE_SEED_PREFIX: &[u8] = b"amm_batch_queue";
+pub const ALLOWLIST_ENTRY_SEED_PREFIX: &[u8] = b"amm_allowlist_entry";
+pub const REWARD_POOL_SEED_PREFIX: &[u8] = b"amm_reward_pool";
+pub const LP_LOCKER_SEED_PREFIX: &[u8] = b"amm_lp_locker";
+pub const LP_POSITION_SEED_PREFIX: &[u8] = b"amm_lp_position";
+pub const STAKE_ACCOUNT_SEED_PREFIX: &[u8] = b"amm_stake_account";
+pub const REFERRER_SEED_PREFIX: &[u8] = b"amm_referrer";
+pub const NATIVE_SOL_ACCOUNT_SEED_PREFIX: &[u8] = b"amm_native_sol_account";
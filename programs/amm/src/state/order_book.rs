@@ -0,0 +1,480 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+use crate::state::SwapType;
+use crate::{MAX_PRICE, PRICE_SCALE};
+
+/// How many resting orders each side of a book can hold. Once a side is
+/// full, a better-priced order evicts the worst one.
+pub const MAX_ORDERS_PER_SIDE: usize = 32;
+
+/// How many makers a book can owe tokens to or hold orders for at once.
+/// Every maker with a resting order has a balance, so fills never need a new
+/// one. That's one more than the most makers that can have orders, so once
+/// the balances of makers without orders are settled, which anyone can do,
+/// a new maker can always get in.
+pub const MAX_MAKER_BALANCES: usize = 2 * MAX_ORDERS_PER_SIDE + 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub enum OrderSide {
+  /// Buy base tokens with escrowed quote tokens
+  Bid,
+  /// Sell escrowed base tokens for quote tokens
+  Ask,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct Order {
+  pub order_id: u64,
+  pub owner: Pubkey,
+  pub side: OrderSide,
+  /// Quote units per base unit, scaled by 1e12 like the oracle's prices
+  pub price: u128,
+  /// Base tokens still to be bought, for bids, or sold, for asks
+  pub base_remaining: u64,
+  /// What's held in the pool's vaults for the unfilled part of the order:
+  /// quote tokens for bids, base tokens for asks
+  pub escrow_remaining: u64,
+}
+
+impl Order {
+  pub fn is_filled(&self) -> bool {
+    self.base_remaining == 0
+  }
+
+  /// The order's escrow as (base, quote).
+  fn escrow(&self) -> (u64, u64) {
+    match self.side {
+      OrderSide::Bid => (0, self.escrow_remaining),
+      OrderSide::Ask => (self.escrow_remaining, 0),
+    }
+  }
+}
+
+/// What a book owes a maker for orders that have filled or been taken off
+/// the book, until it's settled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct MakerBalance {
+  pub owner: Pubkey,
+  pub base_amount: u64,
+  pub quote_amount: u64,
+}
+
+/// Limit orders that rest alongside an `Amm`'s curve. Their tokens are held in
+/// the pool's vaults but never counted in its reserves, so they don't change
+/// the curve's price or what LPs can withdraw.
+#[account]
+#[derive(InitSpace)]
+pub struct OrderBook {
+  pub amm: Pubkey,
+  pub bump: u8,
+  pub next_order_id: u64,
+  /// The smallest order, in base units, set by the pool's admin so that
+  /// filling the book with dust costs something
+  pub min_base_amount: u64,
+  /// Best first: highest price, then oldest
+  #[max_len(MAX_ORDERS_PER_SIDE)]
+  pub bids: Vec<Order>,
+  /// Best first: lowest price, then oldest
+  #[max_len(MAX_ORDERS_PER_SIDE)]
+  pub asks: Vec<Order>,
+  #[max_len(MAX_MAKER_BALANCES)]
+  pub balances: Vec<MakerBalance>,
+}
+
+impl OrderBook {
+  /// Adds an order to the book, returning it so the caller can escrow
+  /// `escrow_remaining` of the right token, along with the order it evicted
+  /// if its side was full. An evicted order's escrow goes to its owner's
+  /// balance.
+  pub fn place(&mut self, owner: Pubkey, side: OrderSide, price: u128, base_amount: u64) -> Result<(Order, Option<Order>)> {
+    require!(base_amount > 0 && base_amount >= self.min_base_amount, AmmError::InvalidOrder);
+    require!(price > 0 && price <= MAX_PRICE, AmmError::InvalidOrder);
+
+    // an order's full quote value has to fit in a token amount, which also
+    // keeps every partial fill's `base * price` within a u128
+    let quote_value = (base_amount as u128)
+      .checked_mul(price)
+      .map(|value| value.div_ceil(PRICE_SCALE))
+      .filter(|value| *value <= u64::MAX as u128)
+      .ok_or(AmmError::InvalidOrder)?;
+
+    let order = Order {
+      order_id: self.next_order_id,
+      owner,
+      side,
+      price,
+      base_remaining: base_amount,
+      escrow_remaining: match side {
+        OrderSide::Bid => quote_value as u64,
+        OrderSide::Ask => base_amount,
+      },
+    };
+
+    // the worst order is last, and only makes way for a strictly better one
+    let is_full = self.side(side).len() >= MAX_ORDERS_PER_SIDE;
+
+    if let Some(worst) = self.side(side).last().filter(|_| is_full) {
+      let is_better = match side {
+        OrderSide::Bid => price > worst.price,
+        OrderSide::Ask => price < worst.price,
+      };
+
+      require!(is_better, AmmError::OrderBookFull);
+    }
+
+    if !self.balances.iter().any(|balance| balance.owner == owner) {
+      require_gt!(MAX_MAKER_BALANCES, self.balances.len(), AmmError::OrderBookFull);
+
+      self.balances.push(MakerBalance {
+        owner,
+        base_amount: 0,
+        quote_amount: 0,
+      });
+    }
+
+    let orders = self.side_mut(side);
+    let evicted = if is_full { orders.pop() } else { None };
+
+    // after every order at the same or a better price, so that ties fill oldest first
+    let position = orders
+      .iter()
+      .position(|resting| match side {
+        OrderSide::Bid => resting.price < price,
+        OrderSide::Ask => resting.price > price,
+      })
+      .unwrap_or(orders.len());
+
+    orders.insert(position, order);
+    self.next_order_id += 1;
+
+    if let Some(evicted) = evicted {
+      let (base_amount, quote_amount) = evicted.escrow();
+      credit(&mut self.balances, evicted.owner, base_amount, quote_amount);
+    }
+
+    Ok((order, evicted))
+  }
+
+  /// Fills resting orders with up to `input_amount` of a swap's input token,
+  /// best price first, and returns (input_used, output_amount).
+  ///
+  /// A buy only takes asks priced at or below `price_bound` and a sell only
+  /// takes bids priced at or above it. Callers pass the curve's spot price,
+  /// or a tighter limit, so that the book only fills where it beats the curve
+  /// and the spot price that feeds the TWAP stays the pool's marginal price.
+  ///
+  /// What each order fills for goes to its owner's balance, and filled
+  /// orders are taken off the book.
+  pub fn fill(&mut self, swap_type: SwapType, input_amount: u64, price_bound: u128) -> (u64, u64) {
+    let mut input_left = input_amount;
+    let mut output_amount = 0;
+
+    match swap_type {
+      SwapType::Buy => {
+        for ask in self.asks.iter_mut().filter(|ask| !ask.is_filled()) {
+          if ask.price > price_bound || input_left == 0 {
+            break;
+          }
+
+          let affordable = input_left as u128 * PRICE_SCALE / ask.price;
+          let base_filled = affordable.min(ask.base_remaining as u128) as u64;
+          if base_filled == 0 {
+            break;
+          }
+
+          // rounds against the taker, can't exceed `input_left` since `base_filled` was affordable
+          let quote_paid = (base_filled as u128 * ask.price).div_ceil(PRICE_SCALE) as u64;
+
+          ask.base_remaining -= base_filled;
+          ask.escrow_remaining -= base_filled;
+          credit(&mut self.balances, ask.owner, 0, quote_paid);
+
+          input_left -= quote_paid;
+          output_amount += base_filled;
+        }
+      }
+      SwapType::Sell => {
+        for bid in self.bids.iter_mut().filter(|bid| !bid.is_filled()) {
+          if bid.price < price_bound || input_left == 0 {
+            break;
+          }
+
+          let base_filled = input_left.min(bid.base_remaining);
+
+          // rounds against the taker, so the escrow, which was rounded up, always covers it
+          let quote_paid = ((base_filled as u128 * bid.price / PRICE_SCALE) as u64).min(bid.escrow_remaining);
+
+          bid.base_remaining -= base_filled;
+          bid.escrow_remaining -= quote_paid;
+          credit(&mut self.balances, bid.owner, base_filled, 0);
+
+          input_left -= base_filled;
+          output_amount += quote_paid;
+        }
+      }
+    }
+
+    // along with any of a filled bid's escrow left over from rounding
+    for side in [OrderSide::Bid, OrderSide::Ask] {
+      let orders = match side {
+        OrderSide::Bid => &mut self.bids,
+        OrderSide::Ask => &mut self.asks,
+      };
+
+      for order in orders.iter().filter(|order| order.is_filled()) {
+        let (base_amount, quote_amount) = order.escrow();
+        credit(&mut self.balances, order.owner, base_amount, quote_amount);
+      }
+
+      orders.retain(|order| !order.is_filled());
+    }
+
+    (input_amount - input_left, output_amount)
+  }
+
+  /// Takes what the book owes a maker off their balance, returning it as
+  /// (base, quote). The balance itself goes once they have no resting orders.
+  pub fn settle(&mut self, owner: Pubkey) -> (u64, u64) {
+    let has_orders = self.bids.iter().chain(&self.asks).any(|order| order.owner == owner);
+    let Some(index) = self.balances.iter().position(|balance| balance.owner == owner) else {
+      return (0, 0);
+    };
+
+    let balance = &mut self.balances[index];
+    let owed = (balance.base_amount, balance.quote_amount);
+
+    if has_orders {
+      balance.base_amount = 0;
+      balance.quote_amount = 0;
+    } else {
+      self.balances.remove(index);
+    }
+
+    owed
+  }
+
+  /// Takes an order off the book and settles its owner, returning (base,
+  /// quote) owed to them: the order's unfilled escrow along with whatever
+  /// else the book owed them.
+  pub fn cancel(&mut self, owner: Pubkey, order_id: u64) -> Result<(u64, u64)> {
+    let (side, index) = self.find(owner, order_id)?;
+    let order = self.side_mut(side).remove(index);
+
+    let (base_amount, quote_amount) = order.escrow();
+    credit(&mut self.balances, owner, base_amount, quote_amount);
+
+    Ok(self.settle(owner))
+  }
+
+  /// How many makers the book owes tokens to or holds orders for.
+  pub fn maker_count(&self) -> u64 {
+    self.balances.len() as u64
+  }
+
+  fn find(&self, owner: Pubkey, order_id: u64) -> Result<(OrderSide, usize)> {
+    let (side, index) = [OrderSide::Bid, OrderSide::Ask]
+      .into_iter()
+      .find_map(|side| {
+        self
+          .side(side)
+          .iter()
+          .position(|order| order.order_id == order_id)
+          .map(|index| (side, index))
+      })
+      .ok_or(AmmError::OrderNotFound)?;
+
+    require_keys_eq!(self.side(side)[index].owner, owner, AmmError::NotOrderOwner);
+
+    Ok((side, index))
+  }
+
+  fn side(&self, side: OrderSide) -> &Vec<Order> {
+    match side {
+      OrderSide::Bid => &self.bids,
+      OrderSide::Ask => &self.asks,
+    }
+  }
+
+  fn side_mut(&mut self, side: OrderSide) -> &mut Vec<Order> {
+    match side {
+      OrderSide::Bid => &mut self.bids,
+      OrderSide::Ask => &mut self.asks,
+    }
+  }
+}
+
+/// Adds to a maker's balance, which every maker with an order already has.
+fn credit(balances: &mut Vec<MakerBalance>, owner: Pubkey, base_amount: u64, quote_amount: u64) {
+  match balances.iter_mut().find(|balance| balance.owner == owner) {
+    Some(balance) => {
+      balance.base_amount += base_amount;
+      balance.quote_amount += quote_amount;
+    }
+    None => balances.push(MakerBalance {
+      owner,
+      base_amount,
+      quote_amount,
+    }),
+  }
+}
+
+#[cfg(test)]
+mod order_book_tests {
+  use super::*;
+
+  fn empty_book() -> OrderBook {
+    OrderBook {
+      amm: Pubkey::default(),
+      bump: 0,
+      next_order_id: 0,
+      min_base_amount: 0,
+      bids: vec![],
+      asks: vec![],
+      balances: vec![],
+    }
+  }
+
+  fn balance(book: &OrderBook, owner: Pubkey) -> (u64, u64) {
+    book
+      .balances
+      .iter()
+      .find(|balance| balance.owner == owner)
+      .map_or((0, 0), |balance| (balance.base_amount, balance.quote_amount))
+  }
+
+  #[test]
+  pub fn orders_sort_by_price_then_time() {
+    let mut book = empty_book();
+    let owner = Pubkey::new_unique();
+
+    book.place(owner, OrderSide::Ask, 3 * PRICE_SCALE, 10).unwrap();
+    book.place(owner, OrderSide::Ask, 2 * PRICE_SCALE, 10).unwrap();
+    book.place(owner, OrderSide::Ask, 2 * PRICE_SCALE, 10).unwrap();
+    book.place(owner, OrderSide::Bid, PRICE_SCALE, 10).unwrap();
+    book.place(owner, OrderSide::Bid, PRICE_SCALE * 3 / 2, 10).unwrap();
+
+    let ask_ids: Vec<u64> = book.asks.iter().map(|order| order.order_id).collect();
+    let bid_ids: Vec<u64> = book.bids.iter().map(|order| order.order_id).collect();
+
+    assert_eq!(ask_ids, vec![1, 2, 0]);
+    assert_eq!(bid_ids, vec![4, 3]);
+
+    // a bid escrows its quote value, rounded up
+    assert_eq!(book.bids[0].escrow_remaining, 15);
+    assert_eq!(book.asks[0].escrow_remaining, 10);
+  }
+
+  #[test]
+  pub fn invalid_orders() {
+    let mut book = empty_book();
+    let owner = Pubkey::new_unique();
+
+    assert!(book.place(owner, OrderSide::Bid, PRICE_SCALE, 0).is_err());
+    assert!(book.place(owner, OrderSide::Bid, 0, 10).is_err());
+    assert!(book.place(owner, OrderSide::Bid, MAX_PRICE + 1, 10).is_err());
+    assert!(book.place(owner, OrderSide::Ask, MAX_PRICE, u64::MAX).is_err());
+
+    book.min_base_amount = 100;
+    assert!(book.place(owner, OrderSide::Ask, PRICE_SCALE, 99).is_err());
+    assert!(book.place(owner, OrderSide::Ask, PRICE_SCALE, 100).is_ok());
+  }
+
+  #[test]
+  pub fn full_sides_evict_the_worst_order() {
+    let mut book = empty_book();
+    let griefer = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+
+    for _ in 0..MAX_ORDERS_PER_SIDE {
+      book.place(griefer, OrderSide::Ask, 10 * PRICE_SCALE, 10).unwrap();
+    }
+
+    // an order at the worst price can't get in, but a better one can
+    assert!(book.place(owner, OrderSide::Ask, 10 * PRICE_SCALE, 10).is_err());
+    let (order, evicted) = book.place(owner, OrderSide::Ask, 9 * PRICE_SCALE, 10).unwrap();
+
+    assert_eq!(book.asks.len(), MAX_ORDERS_PER_SIDE);
+    assert_eq!(book.asks[0].order_id, order.order_id);
+    assert_eq!(evicted.unwrap().order_id, MAX_ORDERS_PER_SIDE as u64 - 1);
+
+    // and the evicted escrow can be settled to its owner
+    assert_eq!(balance(&book, griefer), (10, 0));
+    assert_eq!(book.settle(griefer), (10, 0));
+    assert_eq!(book.maker_count(), 2);
+  }
+
+  #[test]
+  pub fn buys_fill_asks_up_to_the_bound() {
+    let mut book = empty_book();
+    let owner = Pubkey::new_unique();
+
+    book.place(owner, OrderSide::Ask, 2 * PRICE_SCALE, 100).unwrap();
+    book.place(owner, OrderSide::Ask, 3 * PRICE_SCALE, 100).unwrap();
+    book.place(owner, OrderSide::Ask, 5 * PRICE_SCALE, 100).unwrap();
+
+    // 200 quote takes the whole 2.0 ask, then 0 more from the 3.0 ask...
+    assert_eq!(book.fill(SwapType::Buy, 200, 4 * PRICE_SCALE), (200, 100));
+    // ...and 299 quote takes 99 base from the 3.0 ask, leaving 2 quote unused
+    assert_eq!(book.fill(SwapType::Buy, 299, 4 * PRICE_SCALE), (297, 99));
+    // the 5.0 ask is beyond the bound
+    assert_eq!(book.fill(SwapType::Buy, 1_000, 4 * PRICE_SCALE), (3, 1));
+
+    // filled asks are taken off the book, with what they filled for in their owner's balance
+    assert_eq!(book.asks.len(), 1);
+    assert_eq!(book.asks[0].base_remaining, 100);
+    assert_eq!(balance(&book, owner), (0, 500));
+  }
+
+  #[test]
+  pub fn sells_fill_bids_down_to_the_bound() {
+    let mut book = empty_book();
+    let owner = Pubkey::new_unique();
+
+    book.place(owner, OrderSide::Bid, PRICE_SCALE / 3, 100).unwrap();
+    book.place(owner, OrderSide::Bid, PRICE_SCALE / 10, 100).unwrap();
+
+    assert_eq!(book.bids[0].escrow_remaining, 34);
+
+    // rounding favours the maker on every fill, and never overdraws the escrow
+    for _ in 0..100 {
+      assert_eq!(book.fill(SwapType::Sell, 1, PRICE_SCALE / 5), (1, 0));
+    }
+    assert_eq!(book.fill(SwapType::Sell, 1, PRICE_SCALE / 5), (0, 0));
+
+    // the filled bid is taken off the book, and its leftover escrow goes back to its owner
+    assert_eq!(book.bids.len(), 1);
+    assert_eq!(balance(&book, owner), (100, 34));
+  }
+
+  #[test]
+  pub fn settle_and_cancel() {
+    let mut book = empty_book();
+    let owner = Pubkey::new_unique();
+
+    let (bid, _) = book.place(owner, OrderSide::Bid, 2 * PRICE_SCALE, 100).unwrap();
+    let (ask, _) = book.place(owner, OrderSide::Ask, 4 * PRICE_SCALE, 100).unwrap();
+
+    assert!(book.cancel(Pubkey::new_unique(), bid.order_id).is_err());
+    assert!(book.cancel(owner, 42).is_err());
+
+    book.fill(SwapType::Sell, 40, PRICE_SCALE);
+    book.fill(SwapType::Buy, 400, 4 * PRICE_SCALE);
+
+    // the partial fill keeps resting, the full one is gone
+    assert_eq!(book.bids.len(), 1);
+    assert!(book.asks.is_empty());
+
+    // anyone can settle a maker, who keeps their balance while they have orders
+    assert_eq!(book.settle(owner), (40, 400));
+    assert_eq!(book.settle(owner), (0, 0));
+    assert_eq!(book.maker_count(), 1);
+
+    // cancelling returns the unfilled escrow, and with no orders left, the balance goes too
+    book.fill(SwapType::Sell, 10, PRICE_SCALE);
+    assert_eq!(book.cancel(owner, bid.order_id).unwrap(), (10, 100));
+    assert!(book.bids.is_empty());
+    assert_eq!(book.maker_count(), 0);
+    assert!(book.cancel(owner, ask.order_id).is_err());
+  }
+}
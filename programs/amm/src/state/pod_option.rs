@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+
+/// Defines a fixed-layout stand-in for `Option<$ty>`, for zero-copy accounts,
+/// which can't hold an `Option` directly since its layout isn't defined.
+///
+/// The padding is explicit and doesn't depend on the target, so the type
+/// has the same size whether `u128` is 8-byte aligned, as on-chain, or
+/// 16-byte aligned, as on most hosts.
+macro_rules! pod_option {
+  ($name:ident, $ty:ty, $padding:literal) => {
+    #[doc = concat!("An `Option<", stringify!($ty), ">` that can live in a zero-copy account.")]
+    #[zero_copy]
+    #[derive(Default, Debug, PartialEq, Eq)]
+    pub struct $name {
+      value: $ty,
+      is_some: u8,
+      _padding: [u8; $padding],
+    }
+
+    impl $name {
+      pub fn get(&self) -> Option<$ty> {
+        (self.is_some != 0).then_some(self.value)
+      }
+
+      pub fn is_some(&self) -> bool {
+        self.is_some != 0
+      }
+
+      pub fn is_none(&self) -> bool {
+        self.is_some == 0
+      }
+    }
+
+    impl From<Option<$ty>> for $name {
+      fn from(option: Option<$ty>) -> Self {
+        Self {
+          value: option.unwrap_or_default(),
+          is_some: option.is_some() as u8,
+          _padding: [0; $padding],
+        }
+      }
+    }
+  };
+}
+
+pod_option!(OptionalU16, u16, 1);
+pod_option!(OptionalU64, u64, 7);
+pod_option!(OptionalU128, u128, 15);
+pod_option!(OptionalPubkey, Pubkey, 0);
+
+#[cfg(test)]
+mod pod_option_tests {
+  use super::*;
+  use std::mem::size_of;
+
+  #[test]
+  pub fn round_trips() {
+    assert_eq!(OptionalU16::from(Some(0)).get(), Some(0));
+    assert_eq!(OptionalU64::from(None).get(), None);
+    assert_eq!(OptionalU128::from(Some(u128::MAX)).get(), Some(u128::MAX));
+
+    let key = Pubkey::new_unique();
+    assert_eq!(OptionalPubkey::from(Some(key)).get(), Some(key));
+    assert!(OptionalPubkey::default().is_none());
+  }
+
+  #[test]
+  pub fn sizes() {
+    assert_eq!(size_of::<OptionalU16>(), 4);
+    assert_eq!(size_of::<OptionalU64>(), 16);
+    assert_eq!(size_of::<OptionalU128>(), 32);
+    assert_eq!(size_of::<OptionalPubkey>(), 33);
+  }
+}
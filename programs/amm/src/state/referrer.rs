@@ -0,0 +1,11 @@
+use anchor_lang::prelude::*;
+
+/// Lets token accounts owned by `owner` receive the referral slice of swap
+/// fees in a pool. Only the pool's admin can register or remove one.
+#[account]
+#[derive(Default)]
+pub struct Referrer {
+  pub bump: u8,
+  pub amm: Pubkey,
+  pub owner: Pubkey,
+}
@@ -0,0 +1,241 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::clock::Slot;
+
+use crate::error::AmmError;
+
+/// `acc_reward_per_share` is scaled by this so that small emissions shared
+/// by a large stake don't round down to nothing.
+pub const REWARD_PER_SHARE_SCALE: u128 = 1_000_000_000_000;
+
+/// Streams `reward_mint` tokens to the stakers of an `Amm`'s LP token, at
+/// `reward_per_slot` split pro rata between them until `end_slot`. It never
+/// emits more than it has been funded with, so a schedule that outruns its
+/// funding pauses until it's topped up instead of promising rewards that
+/// the vault can't pay.
+#[account]
+#[derive(Default, InitSpace)]
+pub struct RewardPool {
+  pub bump: u8,
+  pub amm: Pubkey,
+  pub lp_mint: Pubkey,
+  pub reward_mint: Pubkey,
+  pub reward_per_slot: u64,
+  pub end_slot: u64,
+  /// The last slot that rewards have been accounted up to
+  pub last_update_slot: u64,
+  /// The rewards that one staked LP token has earned since the pool was
+  /// created, multiplied by `REWARD_PER_SHARE_SCALE`
+  pub acc_reward_per_share: u128,
+  pub total_staked: u64,
+  /// Every reward token that `fund_reward_pool` has put into the vault
+  pub funded_amount: u64,
+  /// Every reward token that has been emitted to stakers, which is at most
+  /// `funded_amount`
+  pub emitted_amount: u64,
+}
+
+#[account]
+#[derive(Default, InitSpace)]
+pub struct StakeAccount {
+  pub bump: u8,
+  pub reward_pool: Pubkey,
+  pub owner: Pubkey,
+  pub staked_amount: u64,
+  /// `acc_reward_per_share` as of the last time this stake's rewards were
+  /// accounted for
+  pub reward_per_share_paid: u128,
+  /// Rewards earned but not yet claimed
+  pub unclaimed_rewards: u64,
+}
+
+impl RewardPool {
+  /// Accounts for the rewards emitted since the last update. Slots where
+  /// nothing was staked emit nothing, and nothing is emitted past what the
+  /// pool has been funded with.
+  pub fn update(&mut self, current_slot: Slot) {
+    let until_slot = current_slot.min(self.end_slot);
+
+    if until_slot <= self.last_update_slot {
+      return;
+    }
+
+    if self.total_staked > 0 {
+      // `create_reward_pool` caps the whole emission at a u64, so this fits
+      let scheduled = (until_slot - self.last_update_slot) * self.reward_per_slot;
+      let emitted = scheduled.min(self.funded_amount - self.emitted_amount);
+
+      self.emitted_amount += emitted;
+      self.acc_reward_per_share += emitted as u128 * REWARD_PER_SHARE_SCALE / self.total_staked as u128;
+    }
+
+    self.last_update_slot = until_slot;
+  }
+
+  /// Records `amount` more reward tokens as funded. Should be called after
+  /// `update`, so that slots that have already passed unfunded don't get
+  /// paid out of it.
+  pub fn fund(&mut self, amount: u64, current_slot: Slot) -> Result<()> {
+    self.update(current_slot);
+
+    self.funded_amount = self.funded_amount.checked_add(amount).ok_or(AmmError::CastingOverflow)?;
+
+    Ok(())
+  }
+
+  /// Moves what `stake_account` has earned into its `unclaimed_rewards`.
+  /// Should be called after `update` and before its stake changes.
+  pub fn accrue(&self, stake_account: &mut StakeAccount) -> Result<()> {
+    // while this stake was in, the pool's stake was at least as big, so this
+    // is at most the whole emission times `REWARD_PER_SHARE_SCALE`
+    let earned = stake_account.staked_amount as u128 * (self.acc_reward_per_share - stake_account.reward_per_share_paid)
+      / REWARD_PER_SHARE_SCALE;
+
+    stake_account.unclaimed_rewards = stake_account
+      .unclaimed_rewards
+      .checked_add(earned.try_into().map_err(|_| AmmError::CastingOverflow)?)
+      .ok_or(AmmError::CastingOverflow)?;
+    stake_account.reward_per_share_paid = self.acc_reward_per_share;
+
+    Ok(())
+  }
+
+  pub fn stake(&mut self, stake_account: &mut StakeAccount, amount: u64, current_slot: Slot) -> Result<()> {
+    require!(amount > 0, AmmError::ZeroStakeAmount);
+
+    self.update(current_slot);
+    self.accrue(stake_account)?;
+
+    stake_account.staked_amount += amount;
+    self.total_staked += amount;
+
+    Ok(())
+  }
+
+  pub fn unstake(&mut self, stake_account: &mut StakeAccount, amount: u64, current_slot: Slot) -> Result<()> {
+    require!(amount > 0, AmmError::ZeroStakeAmount);
+    require_gte!(stake_account.staked_amount, amount, AmmError::InsufficientBalance);
+
+    self.update(current_slot);
+    self.accrue(stake_account)?;
+
+    stake_account.staked_amount -= amount;
+    self.total_staked -= amount;
+
+    Ok(())
+  }
+
+  /// Returns how much of what `stake_account` has earned can be paid out of
+  /// a reward vault holding `available`, and marks it as claimed. Anything
+  /// the vault can't cover yet stays claimable.
+  pub fn claim(&mut self, stake_account: &mut StakeAccount, available: u64, current_slot: Slot) -> Result<u64> {
+    self.update(current_slot);
+    self.accrue(stake_account)?;
+
+    let claimed = stake_account.unclaimed_rewards.min(available);
+    stake_account.unclaimed_rewards -= claimed;
+
+    Ok(claimed)
+  }
+}
+
+#[macro_export]
+macro_rules! generate_reward_pool_seeds {
+  ($reward_pool:expr) => {{
+    &[
+      REWARD_POOL_SEED_PREFIX,
+      $reward_pool.amm.as_ref(),
+      $reward_pool.reward_mint.as_ref(),
+      &[$reward_pool.bump],
+    ]
+  }};
+}
+
+#[cfg(test)]
+mod reward_pool_tests {
+  use super::*;
+
+  fn reward_pool() -> RewardPool {
+    RewardPool {
+      reward_per_slot: 100,
+      end_slot: 1_000,
+      funded_amount: 100_000,
+      ..RewardPool::default()
+    }
+  }
+
+  #[test]
+  pub fn rewards_split_pro_rata() {
+    let mut pool = reward_pool();
+    let mut alice = StakeAccount::default();
+    let mut bob = StakeAccount::default();
+
+    pool.stake(&mut alice, 100, 0).unwrap();
+    // alice earns everything from slot 0 to 10
+    pool.stake(&mut bob, 300, 10).unwrap();
+
+    assert_eq!(pool.claim(&mut alice, u64::MAX, 20).unwrap(), 1_000 + 250);
+    assert_eq!(pool.claim(&mut bob, u64::MAX, 20).unwrap(), 750);
+
+    // nothing more to claim in the same slot
+    assert_eq!(pool.claim(&mut alice, u64::MAX, 20).unwrap(), 0);
+  }
+
+  #[test]
+  pub fn rewards_stop_at_end_slot() {
+    let mut pool = reward_pool();
+    let mut alice = StakeAccount::default();
+
+    pool.stake(&mut alice, 7, 900).unwrap();
+    pool.unstake(&mut alice, 7, 2_000).unwrap();
+
+    assert_eq!(pool.total_staked, 0);
+    assert_eq!(pool.last_update_slot, 1_000);
+    // rounding can only ever lose a token
+    assert!(alice.unclaimed_rewards <= 10_000 && alice.unclaimed_rewards >= 9_999);
+    assert!(pool.unstake(&mut alice, 1, 2_000).is_err());
+  }
+
+  #[test]
+  pub fn unstaked_slots_emit_nothing() {
+    let mut pool = reward_pool();
+    let mut alice = StakeAccount::default();
+
+    pool.update(500);
+    pool.stake(&mut alice, 10, 500).unwrap();
+
+    assert_eq!(pool.claim(&mut alice, u64::MAX, 600).unwrap(), 10_000);
+  }
+
+  #[test]
+  pub fn claims_are_capped_by_the_vault() {
+    let mut pool = reward_pool();
+    let mut alice = StakeAccount::default();
+
+    pool.stake(&mut alice, 10, 0).unwrap();
+
+    assert_eq!(pool.claim(&mut alice, 300, 10).unwrap(), 300);
+    assert_eq!(alice.unclaimed_rewards, 700);
+    assert_eq!(pool.claim(&mut alice, u64::MAX, 10).unwrap(), 700);
+  }
+
+  #[test]
+  pub fn emissions_are_capped_by_the_funding() {
+    let mut pool = RewardPool {
+      funded_amount: 1_500,
+      ..reward_pool()
+    };
+    let mut alice = StakeAccount::default();
+
+    pool.stake(&mut alice, 10, 0).unwrap();
+
+    // 2_000 were scheduled by slot 20, but only 1_500 were funded
+    assert_eq!(pool.claim(&mut alice, u64::MAX, 20).unwrap(), 1_500);
+    assert_eq!(pool.emitted_amount, 1_500);
+
+    // unfunded slots that have passed aren't paid out of later funding
+    pool.fund(500, 30).unwrap();
+    assert_eq!(pool.claim(&mut alice, u64::MAX, 30).unwrap(), 0);
+    assert_eq!(pool.claim(&mut alice, u64::MAX, 40).unwrap(), 500);
+    assert_eq!(pool.emitted_amount, pool.funded_amount);
+  }
+}